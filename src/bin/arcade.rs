@@ -0,0 +1,115 @@
+//! Watch or play the day 13 arcade cabinet in the terminal.
+//!
+//! ```text
+//! cargo run --bin arcade -- [--play] [--record FILE] [--replay FILE]
+//!                           [--dump FILE] [--input FILE]
+//! ```
+//!
+//! Without `--play` the paddle-tracking autoplayer runs the game. `--play`
+//! reads the joystick from the keyboard (a/d/s or the arrow keys, q quits),
+//! `--replay` feeds the inputs of a recorded session back in, `--record`
+//! saves the session for `intcode::replay`, and `--dump` writes every frame
+//! as text to a file instead of drawing to the terminal.
+
+use aoc19::cli::fail;
+use aoc19::day13::{
+    dump_frame, insert_quarters, render_frame, run_session, AutoPlayer, KeyboardPlayer, Player,
+    ScriptedPlayer,
+};
+use aoc19::intcode::replay::Recording;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::process::{Command, Stdio};
+
+/// Puts the terminal into non-canonical, no-echo mode so that single key
+/// presses reach the keyboard player, or back into the normal mode.
+fn raw_terminal(raw: bool) {
+    let args: &[&str] = if raw {
+        &["-icanon", "-echo", "min", "1"]
+    } else {
+        &["icanon", "echo"]
+    };
+    let _ = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status();
+}
+
+fn main() {
+    let mut play = false;
+    let mut input_path = "input/2019/day13.txt".to_string();
+    let mut record_path = None;
+    let mut replay_path = None;
+    let mut dump_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--play" => play = true,
+            "--input" => input_path = value(),
+            "--record" => record_path = Some(value()),
+            "--replay" => replay_path = Some(value()),
+            "--dump" => dump_path = Some(value()),
+            _ => fail(format!("Unknown argument {}", arg)),
+        }
+    }
+
+    let data: Vec<i64> = std::fs::read_to_string(&input_path)
+        .unwrap_or_else(|error| fail(format!("Failed to read {}: {}", input_path, error)))
+        .trim()
+        .split(",")
+        .map(|a| a.parse::<i64>().expect("Failed to parse intcode"))
+        .collect();
+
+    let mut player: Box<dyn Player> = match (&replay_path, play) {
+        (Some(path), _) => {
+            let recording = Recording::load(path).unwrap_or_else(|error| fail(error.to_string()));
+            Box::new(ScriptedPlayer(recording.inputs().collect()))
+        }
+        (None, true) => Box::new(KeyboardPlayer::new(io::stdin())),
+        (None, false) => Box::new(AutoPlayer),
+    };
+
+    let mut program = insert_quarters(data);
+    if record_path.is_some() {
+        program.start_recording();
+    }
+
+    let screen = match &dump_path {
+        Some(path) => {
+            let file = File::create(path).unwrap_or_else(|error| fail(error.to_string()));
+            let mut out = BufWriter::new(file);
+            let mut frame = 0;
+            run_session(&mut program, player.as_mut(), &mut |screen| {
+                dump_frame(&mut out, frame, screen).expect("Failed to write frame");
+                frame += 1;
+            })
+        }
+        None => {
+            if play {
+                raw_terminal(true);
+            }
+            let mut out = io::stdout();
+            let screen = run_session(&mut program, player.as_mut(), &mut |screen| {
+                render_frame(&mut out, screen).expect("Failed to draw frame");
+            });
+            if play {
+                raw_terminal(false);
+            }
+            screen
+        }
+    };
+
+    if let (Some(path), Some(recording)) = (&record_path, program.take_recording()) {
+        recording
+            .save(path)
+            .unwrap_or_else(|error| fail(format!("Failed to save {}: {}", path, error)));
+    }
+
+    println!("Final score: {}", screen.score);
+}
@@ -0,0 +1,79 @@
+//! Saves the pictures drawn by day 8 (the BIOS password) and day 11 (the
+//! hull registration identifier), and day 10's asteroid visibility heatmap,
+//! as image files.
+//!
+//! ```text
+//! cargo run --bin images -- [--format png|pbm|pgm] [--scale N] [--out DIR]
+//! ```
+//!
+//! The images are written as `day8.<format>`, `day10.<format>` and
+//! `day11.<format>` in `DIR`, which defaults to the current directory. Every
+//! pixel becomes an `N` by `N` square, 10 by default.
+
+use aoc19::cli::fail;
+use aoc19::image::Image;
+use aoc19::{day10, day11, day8};
+
+fn read_input(day: u32) -> String {
+    let path = format!("input/2019/day{}.txt", day);
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| fail(format!("Failed to read {}: {}", path, error)))
+}
+
+fn save(image: &Image, scale: usize, path: &str) {
+    image
+        .scaled(scale)
+        .save(path)
+        .unwrap_or_else(|error| fail(format!("Failed to save {}: {}", path, error)));
+    println!("Saved {}", path);
+}
+
+fn main() {
+    let mut format = "png".to_string();
+    let mut scale = 10;
+    let mut out = ".".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .unwrap_or_else(|| fail(format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--format" => format = value,
+            "--scale" => {
+                scale = value
+                    .parse()
+                    .unwrap_or_else(|_| fail(format!("Invalid scale {}", value)))
+            }
+            "--out" => out = value,
+            _ => fail(format!("Unknown argument {}", arg)),
+        }
+    }
+
+    let password = day8::SpaceImage::parse(&read_input(8), day8::WIDTH, day8::HEIGHT)
+        .unwrap_or_else(|error| fail(format!("Failed to parse day 8 input: {}", error)));
+    save(
+        &password.composite().to_image(),
+        scale,
+        &format!("{}/day8.{}", out, format),
+    );
+
+    let asteroids = day10::parse_map(&read_input(10));
+    save(
+        &day10::AsteroidField::new(&asteroids).heatmap_image(),
+        scale,
+        &format!("{}/day10.{}", out, format),
+    );
+
+    let program: Vec<i64> = read_input(11)
+        .trim()
+        .split(",")
+        .map(|a| a.parse::<i64>().expect("Failed to parse intcode"))
+        .collect();
+    save(
+        &day11::registration_image(&program)
+            .unwrap_or_else(|error| fail(format!("Day 11 robot failed: {}", error))),
+        scale,
+        &format!("{}/day11.{}", out, format),
+    );
+}
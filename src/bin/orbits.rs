@@ -0,0 +1,69 @@
+//! Prints day 6's orbit map, either as an indented tree or as Graphviz DOT.
+//!
+//! ```text
+//! cargo run --bin orbits -- tree [--max-depth N] [--sizes]
+//! cargo run --bin orbits -- dot [--path FROM TO] > day6.dot
+//! ```
+//!
+//! `--path` highlights the orbits between two bodies, for example
+//! `--path YOU SAN`.
+
+use aoc19::cli::fail;
+use aoc19::day6::OrbitMap;
+
+fn value(args: &mut impl Iterator<Item = String>, arg: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| fail(format!("{} needs a value", arg)))
+}
+
+fn main() {
+    let path = "input/2019/day6.txt";
+    let input = std::fs::read_to_string(path)
+        .unwrap_or_else(|error| fail(format!("Failed to read {}: {}", path, error)));
+    let map = OrbitMap::parse(&input)
+        .unwrap_or_else(|error| fail(format!("Invalid orbit map:\n{}", error)));
+    let id = |name: String| {
+        map.id(&name)
+            .unwrap_or_else(|| fail(format!("Unknown body {}", name)))
+    };
+
+    let mut args = std::env::args().skip(1);
+    let mode = args.next().unwrap_or_else(|| "tree".to_string());
+
+    match mode.as_str() {
+        "tree" => {
+            let mut tree = map.tree();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--sizes" => tree = tree.sizes(true),
+                    "--max-depth" => {
+                        let depth = value(&mut args, &arg);
+                        tree = tree.max_depth(
+                            depth
+                                .parse()
+                                .unwrap_or_else(|_| fail(format!("Invalid depth {}", depth))),
+                        )
+                    }
+                    _ => fail(format!("Unknown argument {}", arg)),
+                }
+            }
+            print!("{}", tree);
+        }
+        "dot" => {
+            let mut highlight = vec![];
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--path" => {
+                        let (from, to) = (id(value(&mut args, &arg)), id(value(&mut args, &arg)));
+                        highlight = map
+                            .path(from, to)
+                            .unwrap_or_else(|| fail("The bodies are not connected".to_string()));
+                    }
+                    _ => fail(format!("Unknown argument {}", arg)),
+                }
+            }
+            print!("{}", map.to_dot(&highlight));
+        }
+        _ => fail(format!("Unknown mode {}, expected tree or dot", mode)),
+    }
+}
@@ -0,0 +1,9 @@
+//! Helpers shared by the command line tools in `src/bin`.
+
+use std::process::exit;
+
+/// Prints `message` to stderr and exits with status 1.
+pub fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    exit(1)
+}
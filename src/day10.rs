@@ -1,7 +1,15 @@
+use crate::grid::SparseGrid;
+use crate::image::{Image, BLACK, WHITE};
 use std::collections::{HashMap, HashSet};
 
 #[aoc_generator(day10)]
 fn generator_input(input: &str) -> Vec<Asteroid> {
+    parse_map(input)
+}
+
+/// The asteroids on a map where `#` marks an asteroid, with `(0, 0)` at the
+/// top left.
+pub fn parse_map(input: &str) -> Vec<Asteroid> {
     let mut asteroids = vec![];
     for (y, line) in input.lines().enumerate() {
         for (x, c) in line.chars().enumerate() {
@@ -14,45 +22,68 @@ fn generator_input(input: &str) -> Vec<Asteroid> {
     asteroids
 }
 
-#[derive(Clone, Eq, Hash, Debug, PartialEq)]
+/// The reduced offset `(dx, dy)` from an asteroid to the station looking at
+/// it, in map coordinates (`y` grows downwards).
+///
+/// Directions order the way the station's laser sweeps: starting straight
+/// up and turning clockwise. The comparison is exact: it only looks at
+/// which half-turn each direction falls in and at the sign of their cross
+/// product, so no two distinct directions can compare equal however close
+/// their angles are.
+#[derive(Clone, Copy, Eq, Hash, Debug, PartialEq)]
 pub struct Direction(isize, isize);
 
 impl Direction {
-    pub fn angle(&self) -> f64 {
-        let mut angle = (self.1 as f64).atan2(self.0 as f64) - (std::f64::consts::PI / 2.0);
+    /// The direction of `(dx, dy)`, reduced so that parallel offsets compare
+    /// and hash equal.
+    pub fn new(dx: isize, dy: isize) -> Direction {
+        let divisor = gcd(dx, dy).abs().max(1);
+        Direction(dx / divisor, dy / divisor)
+    }
 
-        while angle < 0.0 {
-            angle += 2.0 * std::f64::consts::PI;
-        }
+    /// The offset turned a quarter-turn so that the laser's starting
+    /// direction lies along the positive first axis and the sweep turns
+    /// towards the positive second axis.
+    fn sweep_coordinates(&self) -> (isize, isize) {
+        (self.1, -self.0)
+    }
 
-        angle.to_degrees()
+    /// 0 for the first half of the sweep (including straight up), 1 for the
+    /// second (including straight down).
+    fn half(&self) -> u8 {
+        let (u, v) = self.sweep_coordinates();
+        if v > 0 || (v == 0 && u > 0) {
+            0
+        } else {
+            1
+        }
     }
 }
 
 impl std::cmp::PartialOrd for Direction {
     fn partial_cmp(&self, other: &Direction) -> Option<std::cmp::Ordering> {
-        let self_angle = self.angle();
-        let other_angle = other.angle();
-
-        if self_angle.is_nan() || other_angle.is_nan() {
-            None
-        } else if self_angle > other_angle {
-            Some(std::cmp::Ordering::Greater)
-        } else if other_angle > self_angle {
-            Some(std::cmp::Ordering::Less)
-        } else {
-            Some(std::cmp::Ordering::Equal)
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl std::cmp::Ord for Direction {
+    /// The zero offset, which no pair of distinct asteroids produces, sorts
+    /// before every other direction.
     fn cmp(&self, other: &Direction) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Less)
+        let zero = Direction(0, 0);
+        if *self == zero || *other == zero {
+            return (*other == zero).cmp(&(*self == zero));
+        }
+
+        let (au, av) = self.sweep_coordinates();
+        let (bu, bv) = other.sweep_coordinates();
+        let cross = (au as i128) * (bv as i128) - (av as i128) * (bu as i128);
+
+        self.half().cmp(&other.half()).then_with(|| 0.cmp(&cross))
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Asteroid {
     pub x: isize,
     pub y: isize,
@@ -64,19 +95,18 @@ impl Asteroid {
     }
 
     pub fn distance_to(&self, other: &Asteroid) -> f64 {
-        let dx = (self.x - other.x) as f64;
-        let dy = (self.y - other.y) as f64;
-
-        (dx * dx + dy * dy).sqrt()
+        (self.squared_distance_to(other) as f64).sqrt()
     }
 
-    pub fn direction_to(&self, other: &Asteroid) -> Direction {
+    pub fn squared_distance_to(&self, other: &Asteroid) -> isize {
         let dx = self.x - other.x;
         let dy = self.y - other.y;
 
-        let divisor = gcd(dx, dy).abs();
+        dx * dx + dy * dy
+    }
 
-        Direction(dx / divisor, dy / divisor)
+    pub fn direction_to(&self, other: &Asteroid) -> Direction {
+        Direction::new(self.x - other.x, self.y - other.y)
     }
 }
 
@@ -93,85 +123,337 @@ fn gcd(a: isize, b: isize) -> isize {
 }
 
 #[aoc(day10, part1)]
-fn part_one(asteroids: &Vec<Asteroid>) -> String {
+fn part_one(asteroids: &[Asteroid]) -> String {
     format!("{:?}", find_best_station_count(asteroids))
 }
 
 #[aoc(day10, part2)]
-fn part_two(asteroids: &Vec<Asteroid>) -> String {
-    format!("{:?}", vaporize(asteroids))
+fn part_two(asteroids: &[Asteroid]) -> isize {
+    let (station, _) = find_best_station_count(asteroids);
+    let asteroid = nth_vaporized(Laser::default().sweep(station, asteroids), 200)
+        .expect("Fewer than 200 asteroids can be vaporized");
+
+    asteroid.x * 100 + asteroid.y
 }
 
-fn vaporize(asteroids: &Vec<Asteroid>) -> isize {
-    let (best_station, _) = find_best_station_count(asteroids);
+/// The `n`th asteroid (counting from 1) a sweep vaporizes, or `None` if the
+/// sweep runs out first.
+pub fn nth_vaporized<'a>(mut sweep: Sweep<'a>, n: usize) -> Option<&'a Asteroid> {
+    n.checked_sub(1).and_then(|index| sweep.nth(index))
+}
 
-    let mut list: HashMap<Direction, Vec<&Asteroid>> = HashMap::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    Counterclockwise,
+}
+
+/// How the station's laser sweeps: where it starts pointing, which way it
+/// turns, and whether one shot destroys every asteroid along the beam or
+/// only the nearest.
+#[derive(Debug, Clone, Copy)]
+pub struct Laser {
+    start: Direction,
+    rotation: Rotation,
+    pass_through: bool,
+}
 
-    for asteroid in asteroids {
-        if best_station == asteroid {
-            continue;
+impl Default for Laser {
+    /// Starts pointing up, turns clockwise and stops at the first asteroid
+    /// hit, as in the puzzle.
+    fn default() -> Laser {
+        Laser {
+            start: Direction::new(0, 1),
+            rotation: Rotation::Clockwise,
+            pass_through: false,
         }
+    }
+}
 
-        let direction = best_station.direction_to(asteroid);
-        list.entry(direction).or_default().push(asteroid);
+impl Laser {
+    /// Points the laser along the map offset `(dx, dy)` to begin with. `y`
+    /// grows downwards, so `(0, -1)` is up.
+    pub fn start(mut self, dx: isize, dy: isize) -> Laser {
+        // Directions point from the asteroid back to the station.
+        self.start = Direction::new(-dx, -dy);
+        self
     }
 
-    for asteroids in list.values_mut() {
-        asteroids.sort_by_cached_key(|a| (-best_station.distance_to(a) * 1000.0).round() as isize);
+    pub fn rotation(mut self, rotation: Rotation) -> Laser {
+        self.rotation = rotation;
+        self
     }
 
-    let directions = {
-        let mut directions = list.keys().cloned().collect::<Vec<_>>();
+    pub fn pass_through(mut self, pass_through: bool) -> Laser {
+        self.pass_through = pass_through;
+        self
+    }
+
+    /// The asteroids `station` vaporizes, in order, computed lazily.
+    pub fn sweep<'a>(&self, station: &Asteroid, asteroids: &'a [Asteroid]) -> Sweep<'a> {
+        let mut beams: HashMap<Direction, Vec<&'a Asteroid>> = HashMap::new();
+        for asteroid in asteroids.iter().filter(|a| *a != station) {
+            beams
+                .entry(station.direction_to(asteroid))
+                .or_default()
+                .push(asteroid);
+        }
+
+        let mut directions: Vec<Direction> = beams.keys().cloned().collect();
         directions.sort();
-        directions
-    };
+        if self.rotation == Rotation::Counterclockwise {
+            directions.reverse();
+        }
+        let first = directions.partition_point(|direction| match self.rotation {
+            Rotation::Clockwise => *direction < self.start,
+            Rotation::Counterclockwise => *direction > self.start,
+        });
+        directions.rotate_left(first);
+
+        let beams: Vec<Vec<&'a Asteroid>> = directions
+            .iter()
+            .map(|direction| {
+                let mut beam = beams.remove(direction).unwrap();
+                // Farthest first, so that the nearest can be popped.
+                beam.sort_by_key(|a| std::cmp::Reverse(station.squared_distance_to(a)));
+                beam
+            })
+            .collect();
+
+        Sweep {
+            remaining: beams.iter().map(Vec::len).sum(),
+            beams,
+            beam: 0,
+            pass_through: self.pass_through,
+        }
+    }
+}
 
-    let mut blasted = 0;
-    for direction in directions.iter().cycle() {
-        let asteroids = list.get_mut(direction).unwrap();
+/// An iterator over vaporized asteroids, see `Laser::sweep`.
+pub struct Sweep<'a> {
+    beams: Vec<Vec<&'a Asteroid>>,
+    beam: usize,
+    remaining: usize,
+    pass_through: bool,
+}
 
-        if asteroids.len() > 0 {
-            let asteroid = asteroids.pop().unwrap();
-            blasted += 1;
+impl<'a> Iterator for Sweep<'a> {
+    type Item = &'a Asteroid;
 
-            println!(
-                "Blasting asteroid #{} at ({}, {})",
-                blasted, asteroid.x, asteroid.y
-            );
+    fn next(&mut self) -> Option<&'a Asteroid> {
+        if self.remaining == 0 {
+            return None;
+        }
 
-            if blasted == 200 {
-                return asteroid.x * 100 + asteroid.y;
+        loop {
+            let beam = &mut self.beams[self.beam];
+            if let Some(asteroid) = beam.pop() {
+                if !self.pass_through || beam.is_empty() {
+                    self.beam = (self.beam + 1) % self.beams.len();
+                }
+                self.remaining -= 1;
+                return Some(asteroid);
             }
+            self.beam = (self.beam + 1) % self.beams.len();
         }
     }
 
-    panic!("Failure")
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
-fn find_best_station_count(asteroids: &Vec<Asteroid>) -> (&Asteroid, usize) {
-    let mut best_station = &asteroids[0];
-    let mut best_count = 0;
+impl<'a> ExactSizeIterator for Sweep<'a> {}
 
-    for asteroid in asteroids.iter() {
-        let mut station = HashSet::new();
-        for other in asteroids.iter() {
-            if asteroid != other {
-                station.insert(asteroid.direction_to(other));
-            }
+fn find_best_station_count(asteroids: &[Asteroid]) -> (&Asteroid, usize) {
+    AsteroidField::new(asteroids)
+        .best_station()
+        .expect("Map has no asteroids")
+}
+
+/// Visibility queries over a map of asteroids.
+pub struct AsteroidField<'a> {
+    asteroids: &'a [Asteroid],
+    index: HashMap<Asteroid, usize>,
+}
+
+impl<'a> AsteroidField<'a> {
+    pub fn new(asteroids: &'a [Asteroid]) -> AsteroidField<'a> {
+        AsteroidField {
+            asteroids,
+            index: asteroids
+                .iter()
+                .enumerate()
+                .map(|(i, asteroid)| (*asteroid, i))
+                .collect(),
         }
+    }
+
+    pub fn asteroids(&self) -> &'a [Asteroid] {
+        self.asteroids
+    }
+
+    /// How many other asteroids `station` can see: one per distinct reduced
+    /// direction.
+    pub fn visible_count(&self, station: &Asteroid) -> usize {
+        self.asteroids
+            .iter()
+            .filter(|other| *other != station)
+            .map(|other| station.direction_to(other))
+            .collect::<HashSet<Direction>>()
+            .len()
+    }
+
+    /// `visible_count` for every asteroid, in map order, spread over all
+    /// available cores.
+    pub fn visible_counts(&self) -> Vec<usize> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = self.asteroids.len().div_ceil(threads).max(1);
+
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .asteroids
+                .chunks(chunk)
+                .map(|stations| {
+                    scope.spawn(move || {
+                        stations
+                            .iter()
+                            .map(|station| self.visible_count(station))
+                            .collect::<Vec<usize>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        })
+    }
+
+    /// The asteroid that sees the most others, and how many it sees. Ties go
+    /// to the asteroid listed first.
+    pub fn best_station(&self) -> Option<(&'a Asteroid, usize)> {
+        let counts = self.visible_counts();
+        let best = (0..counts.len()).rev().max_by_key(|&i| counts[i])?;
+        Some((&self.asteroids[best], counts[best]))
+    }
 
-        if station.len() > best_count {
-            best_count = station.len();
-            best_station = asteroid;
+    /// Every asteroid's visible count at its position on the map.
+    pub fn heatmap(&self) -> SparseGrid<usize> {
+        self.asteroids
+            .iter()
+            .zip(self.visible_counts())
+            .map(|(asteroid, count)| ((asteroid.x as i64, asteroid.y as i64), count))
+            .collect()
+    }
+
+    /// The heatmap as text: empty space stays blank and asteroids get a
+    /// character from `.` (sees least) to `@` (sees most).
+    pub fn render_heatmap(&self) -> String {
+        const RAMP: &[u8] = b".:-=+*#%@";
+        let heatmap = self.heatmap();
+        let max = heatmap.values().cloned().max().unwrap_or(0).max(1);
+
+        heatmap
+            .render(|count| match count {
+                Some(count) => RAMP[count * (RAMP.len() - 1) / max] as char,
+                None => ' ',
+            })
+            .to_string()
+    }
+
+    /// The heatmap as a grayscale image, brighter for asteroids that see
+    /// more. `Image::to_pgm` exports it as is and `Image::to_pbm` lights the
+    /// asteroids that see at least half as many as the best one.
+    pub fn heatmap_image(&self) -> Image {
+        let heatmap = self.heatmap();
+        let bounds = match heatmap.bounds() {
+            Some(bounds) => bounds,
+            None => return Image::new(0, 0),
+        };
+        let max = heatmap.values().cloned().max().unwrap_or(0).max(1);
+
+        Image::from_fn(bounds.width(), bounds.height(), |x, y| {
+            let position = (bounds.min_x + x as i64, bounds.min_y + y as i64);
+            match heatmap.get(position.0, position.1) {
+                Some(count) => (count * usize::from(WHITE) / max) as u8,
+                None => BLACK,
+            }
+        })
+    }
+
+    /// The asteroids `station` can see, in the order the laser would reach
+    /// them.
+    pub fn visible_from(&self, station: &Asteroid) -> Vec<&'a Asteroid> {
+        let mut nearest: HashMap<Direction, &'a Asteroid> = HashMap::new();
+        for other in self.asteroids.iter().filter(|other| *other != station) {
+            let current = nearest.entry(station.direction_to(other)).or_insert(other);
+            if station.squared_distance_to(other) < station.squared_distance_to(current) {
+                *current = other;
+            }
         }
+
+        let mut visible: Vec<(Direction, &'a Asteroid)> = nearest.into_iter().collect();
+        visible.sort_by_key(|(direction, _)| *direction);
+        visible.into_iter().map(|(_, asteroid)| asteroid).collect()
+    }
+
+    /// The asteroid nearest to `from` that lies exactly on the straight line
+    /// to `to`, between the two.
+    pub fn blocker(&self, from: &Asteroid, to: &Asteroid) -> Option<&'a Asteroid> {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let steps = gcd(dx, dy).abs();
+        (1..steps)
+            .map(|k| Asteroid::new(from.x + dx / steps * k, from.y + dy / steps * k))
+            .find_map(|point| self.index.get(&point))
+            .map(|&i| &self.asteroids[i])
     }
 
-    (best_station, best_count)
+    pub fn line_of_sight(&self, from: &Asteroid, to: &Asteroid) -> bool {
+        from != to && self.blocker(from, to).is_none()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{find_best_station_count, generator_input, Asteroid};
+    use super::{
+        find_best_station_count, generator_input, nth_vaporized, Asteroid, AsteroidField,
+        Direction, Laser, Rotation,
+    };
+
+    const LARGE_EXAMPLE: &str = "\
+.#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##";
+
+    const SMALL_EXAMPLE: &str = "\
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##";
+
+    fn positions<'a>(asteroids: impl Iterator<Item = &'a Asteroid>) -> Vec<(isize, isize)> {
+        asteroids.map(|a| (a.x, a.y)).collect()
+    }
 
     #[test]
     fn basic_test() {
@@ -181,4 +463,204 @@ mod tests {
 
         assert_eq!(count, 8);
     }
+
+    /// The old floating point sweep angle, in radians from straight up.
+    fn atan2_angle(dx: isize, dy: isize) -> f64 {
+        let angle = (dy as f64).atan2(dx as f64) - std::f64::consts::FRAC_PI_2;
+        if angle < 0.0 {
+            angle + 2.0 * std::f64::consts::PI
+        } else {
+            angle
+        }
+    }
+
+    #[test]
+    fn test_direction_order_agrees_with_atan2() {
+        let offsets: Vec<(isize, isize)> = (-7..=7)
+            .flat_map(|dx| (-7..=7).map(move |dy| (dx, dy)))
+            .filter(|&offset| offset != (0, 0))
+            .collect();
+
+        for &(ax, ay) in offsets.iter() {
+            for &(bx, by) in offsets.iter() {
+                let exact = Direction::new(ax, ay).cmp(&Direction::new(bx, by));
+                let float = atan2_angle(ax, ay)
+                    .partial_cmp(&atan2_angle(bx, by))
+                    .unwrap();
+                let parallel = ax * by == ay * bx && (ax * bx + ay * by) > 0;
+
+                if parallel {
+                    assert_eq!(
+                        exact,
+                        std::cmp::Ordering::Equal,
+                        "{:?} {:?}",
+                        (ax, ay),
+                        (bx, by)
+                    );
+                } else {
+                    assert_eq!(exact, float, "{:?} {:?}", (ax, ay), (bx, by));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_direction_order_is_exact_for_close_slopes() {
+        // atan2 can't tell these apart in f64: the slopes differ by ~1e-18.
+        let a = Direction::new(-1_000_000_000, 999_999_999);
+        let b = Direction::new(-999_999_999, 999_999_998);
+        assert!(a < b);
+        assert_eq!(Direction::new(0, 5), Direction::new(0, 1));
+        assert!(Direction::new(0, 1) < Direction::new(-1, 0));
+    }
+
+    #[test]
+    fn test_sweep_large_example() {
+        let asteroids = generator_input(LARGE_EXAMPLE);
+        let (station, count) = find_best_station_count(&asteroids);
+        assert_eq!((station.x, station.y, count), (11, 13, 210));
+
+        let order = positions(Laser::default().sweep(station, &asteroids));
+        assert_eq!(order.len(), asteroids.len() - 1);
+        for &(n, position) in [
+            (1, (11, 12)),
+            (2, (12, 1)),
+            (3, (12, 2)),
+            (10, (12, 8)),
+            (20, (16, 0)),
+            (50, (16, 9)),
+            (100, (10, 16)),
+            (199, (9, 6)),
+            (200, (8, 2)),
+            (201, (10, 9)),
+            (299, (11, 1)),
+        ]
+        .iter()
+        {
+            assert_eq!(order[n - 1], position, "asteroid #{}", n);
+        }
+
+        let sweep = || Laser::default().sweep(station, &asteroids);
+        assert_eq!(nth_vaporized(sweep(), 299).unwrap(), &Asteroid::new(11, 1));
+        assert_eq!(nth_vaporized(sweep(), 300), None);
+        assert_eq!(nth_vaporized(sweep(), 0), None);
+    }
+
+    #[test]
+    fn test_sweep_small_example() {
+        let asteroids = generator_input(SMALL_EXAMPLE);
+        let station = Asteroid::new(8, 3);
+        let order = positions(Laser::default().sweep(&station, &asteroids).take(9));
+
+        assert_eq!(
+            order,
+            vec![
+                (8, 1),
+                (9, 0),
+                (9, 1),
+                (10, 0),
+                (9, 2),
+                (11, 1),
+                (12, 1),
+                (11, 2),
+                (15, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counterclockwise_mirrors_clockwise() {
+        let asteroids = generator_input(LARGE_EXAMPLE);
+        let mirrored: Vec<Asteroid> = asteroids
+            .iter()
+            .map(|a| Asteroid::new(19 - a.x, a.y))
+            .collect();
+        let station = Asteroid::new(11, 13);
+
+        let clockwise = positions(Laser::default().sweep(&station, &asteroids));
+        let counterclockwise = positions(
+            Laser::default()
+                .rotation(Rotation::Counterclockwise)
+                .sweep(&Asteroid::new(8, 13), &mirrored),
+        );
+        let unmirrored: Vec<(isize, isize)> =
+            counterclockwise.iter().map(|&(x, y)| (19 - x, y)).collect();
+
+        assert_eq!(unmirrored, clockwise);
+    }
+
+    #[test]
+    fn test_start_direction_and_pass_through() {
+        // The station is at (1, 2); two asteroids above it, one to the right
+        // and one below.
+        let asteroids = generator_input(".#.\n.#.\n.##\n.#.");
+        let station = Asteroid::new(1, 2);
+
+        let order = positions(Laser::default().sweep(&station, &asteroids));
+        assert_eq!(order, vec![(1, 1), (2, 2), (1, 3), (1, 0)]);
+
+        let order = positions(
+            Laser::default()
+                .pass_through(true)
+                .sweep(&station, &asteroids),
+        );
+        assert_eq!(order, vec![(1, 1), (1, 0), (2, 2), (1, 3)]);
+
+        let order = positions(
+            Laser::default()
+                .start(0, 1)
+                .rotation(Rotation::Counterclockwise)
+                .sweep(&station, &asteroids),
+        );
+        assert_eq!(order, vec![(1, 3), (2, 2), (1, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let asteroids = generator_input(".#..#\n.....\n#####\n....#\n...##");
+        let field = AsteroidField::new(&asteroids);
+
+        let digits = field
+            .heatmap()
+            .render(|count| count.map_or('.', |&n| (b'0' + n as u8) as char))
+            .to_string();
+        assert_eq!(digits, ".7..7\n.....\n67775\n....7\n...87\n");
+        assert_eq!(
+            field.render_heatmap(),
+            " %  %\n     \n#%%%*\n    %\n   @%\n"
+        );
+
+        let image = field.heatmap_image();
+        assert_eq!((image.width, image.height), (5, 5));
+        assert_eq!(image.get(3, 4), 255);
+        assert_eq!(image.get(0, 2), 191);
+        assert_eq!(image.get(0, 0), 0);
+    }
+
+    #[test]
+    fn test_visible_counts_match_serial_scan() {
+        let asteroids = generator_input(LARGE_EXAMPLE);
+        let field = AsteroidField::new(&asteroids);
+        let serial: Vec<usize> = asteroids.iter().map(|a| field.visible_count(a)).collect();
+
+        assert_eq!(field.visible_counts(), serial);
+        assert_eq!(field.visible_from(&Asteroid::new(11, 13)).len(), 210);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let asteroids = generator_input(".#..#\n.....\n#####\n....#\n...##");
+        let field = AsteroidField::new(&asteroids);
+        let at = |x, y| Asteroid::new(x, y);
+
+        assert_eq!(field.blocker(&at(0, 2), &at(4, 2)), Some(&at(1, 2)));
+        assert_eq!(field.blocker(&at(3, 4), &at(1, 0)), Some(&at(2, 2)));
+        assert!(field.line_of_sight(&at(3, 4), &at(4, 0)));
+        assert!(!field.line_of_sight(&at(3, 4), &at(3, 4)));
+
+        let visible = field.visible_from(&at(1, 0));
+        assert_eq!(visible.len(), 7);
+        assert!(!visible.contains(&&at(3, 4)));
+        assert!(visible.iter().all(|a| field.line_of_sight(&at(1, 0), a)));
+    }
 }
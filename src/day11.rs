@@ -1,9 +1,13 @@
+use crate::grid::{SparseGrid, YAxis};
+use crate::image::Image;
 use crate::intcode::{Action, Program};
-use std::collections::HashMap;
+use crate::ocr;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::fmt;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Color {
+pub enum Color {
     Black,
     White,
 }
@@ -18,18 +22,57 @@ impl From<Color> for i64 {
 }
 
 impl TryFrom<i64> for Color {
-    type Error = ();
+    type Error = RobotError;
 
     fn try_from(value: i64) -> Result<Color, Self::Error> {
         match value {
             0 => Ok(Color::Black),
             1 => Ok(Color::White),
-            _ => Err(()),
+            _ => Err(RobotError::UnknownColor(value)),
         }
     }
 }
 
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl TryFrom<i64> for Turn {
+    type Error = RobotError;
+
+    fn try_from(value: i64) -> Result<Turn, Self::Error> {
+        match value {
+            0 => Ok(Turn::Left),
+            1 => Ok(Turn::Right),
+            _ => Err(RobotError::UnknownTurn(value)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RobotError {
+    UnknownColor(i64),
+    UnknownTurn(i64),
+    /// The brain chose a colour but stopped before choosing a turn.
+    MissingTurn,
+}
+
+impl fmt::Display for RobotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RobotError::UnknownColor(value) => write!(f, "Unknown colour {}", value),
+            RobotError::UnknownTurn(value) => write!(f, "Unknown turn {}", value),
+            RobotError::MissingTurn => write!(f, "Brain painted a panel without turning"),
+        }
+    }
+}
+
+impl std::error::Error for RobotError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Up,
     Down,
     Left,
@@ -37,13 +80,13 @@ enum Direction {
 }
 
 impl Direction {
-    fn get_turn(&self, turn_dir: i64) -> Direction {
-        match turn_dir {
-            0 => self.get_left(),
-            1 => self.get_right(),
-            _ => panic!(),
+    fn get_turn(&self, turn: Turn) -> Direction {
+        match turn {
+            Turn::Left => self.get_left(),
+            Turn::Right => self.get_right(),
         }
     }
+
     fn get_right(&self) -> Direction {
         match self {
             Direction::Up => Direction::Right,
@@ -62,7 +105,7 @@ impl Direction {
         }
     }
 
-    fn move_forward(&self) -> (isize, isize) {
+    fn move_forward(&self) -> (i64, i64) {
         match self {
             Direction::Up => (0, 1),
             Direction::Down => (0, -1),
@@ -72,81 +115,299 @@ impl Direction {
     }
 }
 
-#[aoc_generator(day11)]
-fn generator_input(input: &str) -> Vec<i64> {
-    input
-        .split(",")
-        .map(|a| a.parse::<i64>().unwrap())
-        .collect()
+/// Decides what the robot does on each panel: given the colour under it,
+/// the colour to paint and which way to turn, or `None` to stop.
+pub trait Brain {
+    fn think(&mut self, color: Color) -> Result<Option<(Color, Turn)>, RobotError>;
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct Position(isize, isize);
+/// The puzzle's brain: an intcode program that reads the panel colour and
+/// outputs a colour and a turn, until it halts.
+pub struct IntcodeBrain {
+    program: Program,
+}
 
-fn paint(data: &Vec<i64>, color: Color) -> HashMap<Position, Color> {
-    let mut program = Program::new(data.to_vec(), vec![0]).halt_on_output();
-    program.set_available_memory(2000);
-    let mut panels: HashMap<Position, Color> = Default::default();
-    let mut facing = Direction::Up;
-    let mut position = Position(0, 0);
-    panels.insert(position, color);
+impl IntcodeBrain {
+    pub fn new(data: &[i64]) -> IntcodeBrain {
+        let mut program = Program::new(data.to_vec(), vec![]).halt_on_output();
+        program.set_available_memory(2000);
+        IntcodeBrain { program }
+    }
+}
+
+impl Brain for IntcodeBrain {
+    fn think(&mut self, color: Color) -> Result<Option<(Color, Turn)>, RobotError> {
+        self.program.write_input(i64::from(color));
 
-    'main: loop {
-        let paint_action = program.execute();
-        if paint_action == Action::Halt {
-            break 'main;
+        let paint = match self.program.execute() {
+            Action::Output(value) => Color::try_from(value)?,
+            _ => return Ok(None),
+        };
+        match self.program.execute() {
+            Action::Output(value) => Ok(Some((paint, Turn::try_from(value)?))),
+            _ => Err(RobotError::MissingTurn),
         }
+    }
+}
 
-        let dir_action = program.execute();
+/// A brain that follows a closure instead of a program, for testing the
+/// robot on known patterns. The closure returns `None` to stop.
+pub struct ScriptedBrain {
+    rule: Box<dyn FnMut(Color) -> Option<(Color, Turn)>>,
+}
 
-        if let Action::Output(color) = paint_action {
-            let paint_color = Color::try_from(color).unwrap_or(Color::Black);
-            panels.insert(position, paint_color);
+impl ScriptedBrain {
+    pub fn new<F: FnMut(Color) -> Option<(Color, Turn)> + 'static>(rule: F) -> ScriptedBrain {
+        ScriptedBrain {
+            rule: Box::new(rule),
         }
+    }
 
-        if let Action::Output(turn) = dir_action {
-            facing = facing.get_turn(turn);
-            let (dx, dy) = facing.move_forward();
-            position.0 += dx;
-            position.1 += dy;
+    /// Makes the given moves in order, whatever the panels look like.
+    pub fn moves(moves: Vec<(Color, Turn)>) -> ScriptedBrain {
+        let mut moves = VecDeque::from(moves);
+        ScriptedBrain::new(move |_| moves.pop_front())
+    }
+
+    /// Langton's ant for `steps` steps: turn right on black and left on
+    /// white, flipping the colour of the panel either way.
+    pub fn langtons_ant(steps: usize) -> ScriptedBrain {
+        let mut remaining = steps;
+        ScriptedBrain::new(move |color| {
+            remaining = remaining.checked_sub(1)?;
+            Some(match color {
+                Color::Black => (Color::White, Turn::Right),
+                Color::White => (Color::Black, Turn::Left),
+            })
+        })
+    }
+}
+
+impl Brain for ScriptedBrain {
+    fn think(&mut self, color: Color) -> Result<Option<(Color, Turn)>, RobotError> {
+        Ok((self.rule)(color))
+    }
+}
+
+/// One move of the robot: where it was, what it saw there and what it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub position: (i64, i64),
+    pub seen: Color,
+    pub painted: Color,
+    pub turn: Turn,
+}
+
+/// The painting robot and the hull it works on. Panels are keyed with `y`
+/// pointing up, the direction the robot starts out facing, and only panels
+/// that have been painted (plus the starting panel) are stored.
+pub struct HullRobot {
+    start: Color,
+    panels: SparseGrid<Color>,
+    position: (i64, i64),
+    facing: Direction,
+    history: Vec<Step>,
+}
+
+impl HullRobot {
+    /// A robot at the origin, facing up, on a black hull except for the
+    /// panel under it, which has `start`.
+    pub fn new(start: Color) -> HullRobot {
+        let mut panels = SparseGrid::new(YAxis::Up);
+        panels.insert(0, 0, start);
+        HullRobot {
+            start,
+            panels,
+            position: (0, 0),
+            facing: Direction::Up,
+            history: vec![],
         }
+    }
 
-        program.write_input(*panels.entry(position).or_insert(Color::Black) as i64);
+    pub fn panels(&self) -> &SparseGrid<Color> {
+        &self.panels
     }
 
-    panels
+    pub fn position(&self) -> (i64, i64) {
+        self.position
+    }
+
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+
+    pub fn history(&self) -> &[Step] {
+        &self.history
+    }
+
+    fn color(&self, (x, y): (i64, i64)) -> Color {
+        self.panels.get(x, y).cloned().unwrap_or(Color::Black)
+    }
+
+    /// Lets `brain` paint the current panel and moves on. Returns `false`
+    /// once the brain has stopped.
+    pub fn step(&mut self, brain: &mut dyn Brain) -> Result<bool, RobotError> {
+        let seen = self.color(self.position);
+        let (painted, turn) = match brain.think(seen)? {
+            Some(decision) => decision,
+            None => return Ok(false),
+        };
+
+        self.panels
+            .insert(self.position.0, self.position.1, painted);
+        self.history.push(Step {
+            position: self.position,
+            seen,
+            painted,
+            turn,
+        });
+
+        self.facing = self.facing.get_turn(turn);
+        let (dx, dy) = self.facing.move_forward();
+        self.position = (self.position.0 + dx, self.position.1 + dy);
+
+        Ok(true)
+    }
+
+    pub fn run(&mut self, brain: &mut dyn Brain) -> Result<(), RobotError> {
+        while self.step(brain)? {}
+        Ok(())
+    }
+
+    /// The hull as it looked after the first `steps` moves, for animating a
+    /// finished run.
+    pub fn snapshot(&self, steps: usize) -> SparseGrid<Color> {
+        let mut panels = SparseGrid::new(YAxis::Up);
+        panels.insert(0, 0, self.start);
+        for step in self.history.iter().take(steps) {
+            panels.insert(step.position.0, step.position.1, step.painted);
+        }
+
+        panels
+    }
+}
+
+#[aoc_generator(day11)]
+fn generator_input(input: &str) -> Vec<i64> {
+    input
+        .split(",")
+        .map(|a| a.parse::<i64>().unwrap())
+        .collect()
+}
+
+/// Runs the intcode painting robot on a hull whose starting panel has
+/// `color`.
+pub fn paint(data: &[i64], color: Color) -> Result<HullRobot, RobotError> {
+    let mut robot = HullRobot::new(color);
+    robot.run(&mut IntcodeBrain::new(data))?;
+    Ok(robot)
+}
+
+/// The number of distinct panels painted at least once.
+fn painted_panels(robot: &HullRobot) -> usize {
+    let mut positions: Vec<(i64, i64)> = robot.history().iter().map(|s| s.position).collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions.len()
 }
 
 #[aoc(day11, part1)]
-fn part_one(data: &Vec<i64>) -> String {
-    let panels = paint(data, Color::Black);
-    format!("{}", panels.len())
+fn part_one(data: &[i64]) -> Result<usize, RobotError> {
+    Ok(painted_panels(&paint(data, Color::Black)?))
+}
+
+/// Draws the painted panels with white panels lit.
+pub fn hull_image(panels: &SparseGrid<Color>) -> Image {
+    panels.to_image(|&color| color == Color::White)
+}
+
+/// Paints the hull starting from a single white panel, which makes the robot
+/// spell out the registration identifier.
+pub fn registration_image(data: &[i64]) -> Result<Image, RobotError> {
+    Ok(hull_image(paint(data, Color::White)?.panels()))
 }
 
 #[aoc(day11, part2)]
-fn part_two(data: &Vec<i64>) -> String {
-    let panels = paint(data, Color::White);
-
-    let min_x = panels.keys().map(|p| p.0).min().unwrap();
-    let max_x = panels.keys().map(|p| p.0).max().unwrap();
-    let min_y = panels.keys().map(|p| p.1).min().unwrap();
-    let max_y = panels.keys().map(|p| p.1).max().unwrap();
-
-    let mut output = String::new();
-    for y in min_y..max_y {
-        for x in min_x..max_x {
-            output.push(
-                match panels.get(&Position(x, y)).cloned().unwrap_or(Color::Black) {
-                    Color::Black => ' ',
-                    Color::White => '█',
-                },
-            )
-        }
+fn part_two(data: &[i64]) -> Result<String, RobotError> {
+    Ok(ocr::read_or_show(&registration_image(data)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hull_image, painted_panels, Color, HullRobot, IntcodeBrain, RobotError, ScriptedBrain, Turn,
+    };
+    use crate::grid::{SparseGrid, YAxis};
+
+    #[test]
+    fn test_hull_image_keeps_edges_and_puts_up_on_top() {
+        let mut panels = SparseGrid::new(YAxis::Up);
+        panels.insert(0, 0, Color::White);
+        panels.insert(1, 0, Color::Black);
+        panels.insert(2, 0, Color::White);
+        panels.insert(2, 2, Color::White);
 
-        output.push('\n');
+        assert_eq!(hull_image(&panels).to_string(), "  █\n   \n█ █\n");
     }
 
-    println!("{}", output);
+    #[test]
+    fn test_puzzle_example() {
+        use Color::{Black, White};
+        use Turn::{Left, Right};
 
-    format!("{}", panels.len())
+        let mut robot = HullRobot::new(Black);
+        let mut brain = ScriptedBrain::moves(vec![
+            (White, Left),
+            (Black, Left),
+            (White, Left),
+            (White, Left),
+            (Black, Right),
+            (White, Left),
+            (White, Left),
+        ]);
+        robot.run(&mut brain).unwrap();
+
+        assert_eq!(painted_panels(&robot), 6);
+        assert_eq!(robot.position(), (0, 1));
+        assert_eq!(robot.history()[4].seen, White);
+        assert_eq!(hull_image(&robot.snapshot(4)).to_string(), " █\n██\n");
+    }
+
+    #[test]
+    fn test_langtons_ant() {
+        let mut robot = HullRobot::new(Color::Black);
+        robot.run(&mut ScriptedBrain::langtons_ant(4)).unwrap();
+        assert_eq!(robot.position(), (0, 0));
+        assert_eq!(hull_image(robot.panels()).to_string(), "██\n██\n");
+
+        robot.run(&mut ScriptedBrain::langtons_ant(1)).unwrap();
+        assert_eq!(robot.history().len(), 5);
+        assert_eq!(robot.panels().get(0, 0), Some(&Color::Black));
+    }
+
+    #[test]
+    fn test_intcode_brain_sees_the_starting_panel() {
+        // Paint the panel with the colour read from it, turn left and halt.
+        let echo = [3, 100, 4, 100, 104, 0, 99];
+        for &start in &[Color::Black, Color::White] {
+            let mut robot = HullRobot::new(start);
+            robot.run(&mut IntcodeBrain::new(&echo)).unwrap();
+            assert_eq!(robot.history()[0].seen, start);
+            assert_eq!(robot.history()[0].painted, start);
+        }
+    }
+
+    #[test]
+    fn test_intcode_brain_errors() {
+        let mut robot = HullRobot::new(Color::Black);
+        let mut brain = IntcodeBrain::new(&[104, 5, 104, 0, 99]);
+        assert_eq!(robot.run(&mut brain), Err(RobotError::UnknownColor(5)));
+
+        let mut brain = IntcodeBrain::new(&[104, 1, 104, 7, 99]);
+        assert_eq!(robot.run(&mut brain), Err(RobotError::UnknownTurn(7)));
+
+        let mut brain = IntcodeBrain::new(&[104, 1, 99]);
+        assert_eq!(robot.run(&mut brain), Err(RobotError::MissingTurn));
+        assert!(robot.history().is_empty());
+    }
 }
@@ -1,43 +1,112 @@
-use nalgebra as na;
-use std::collections::HashSet;
-use std::fmt;
 use num::integer::lcm;
+use std::fmt;
+use std::io::{self, Write};
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::thread;
+
+/// Axis names used when parsing and printing moons, for up to four
+/// dimensions. Further axes are printed by number and parsed by position.
+const AXES: [&str; 4] = ["x", "y", "z", "w"];
 
 #[aoc_generator(day12)]
-fn generator_input(input: &str) -> Vec<Moon> {
+fn generator_input(input: &str) -> Result<Vec<Moon>, MoonParseError> {
+    parse_moons(input)
+}
+
+/// Parses one moon per non-empty line.
+pub fn parse_moons<const D: usize>(input: &str) -> Result<Vec<Moon<D>>, MoonParseError> {
     input
         .lines()
-        .map(|line| {
-            let linepart = &line[1..line.len() - 1];
-            let parts = linepart.split(",").collect::<Vec<_>>();
-            let x: isize = parts[0].trim()[2..].parse().unwrap();
-            let y: isize = parts[1].trim()[2..].parse().unwrap();
-            let z: isize = parts[2].trim()[2..].parse().unwrap();
-            Moon::new(x, y, z)
-        })
+        .filter(|line| !line.trim().is_empty())
+        .map(Moon::from_str)
         .collect()
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct Moon {
-    pub position: na::Vector3<isize>,
-    pub velocity: na::Vector3<isize>,
+#[derive(Debug, PartialEq)]
+pub enum MoonParseError {
+    IntError(ParseIntError),
+    Malformed(String),
+}
+
+impl From<ParseIntError> for MoonParseError {
+    fn from(pie: ParseIntError) -> Self {
+        MoonParseError::IntError(pie)
+    }
+}
+
+impl fmt::Display for MoonParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoonParseError::IntError(pie) => write!(f, "Error parsing integer: {}", pie),
+            MoonParseError::Malformed(line) => {
+                write!(f, "Expected <x=.., y=.., ...>, found {:?}", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoonParseError {}
+
+impl<const D: usize> FromStr for Moon<D> {
+    type Err = MoonParseError;
+
+    /// Parses a scan line such as `<x=-1, y=0, z=2>`, with exactly `D`
+    /// coordinates.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || MoonParseError::Malformed(s.to_string());
+        let inner = s
+            .trim()
+            .strip_prefix('<')
+            .and_then(|inner| inner.strip_suffix('>'))
+            .ok_or_else(malformed)?;
+
+        let parts: Vec<&str> = inner.split(',').collect();
+        if parts.len() != D {
+            return Err(malformed());
+        }
+
+        let mut position = [0; D];
+        for (axis, (part, coordinate)) in parts.iter().zip(&mut position).enumerate() {
+            let (name, value) = part.trim().split_once('=').ok_or_else(malformed)?;
+            if AXES.get(axis).is_some_and(|expected| *expected != name) {
+                return Err(malformed());
+            }
+            *coordinate = value.parse()?;
+        }
+
+        Ok(Moon::at(position))
+    }
+}
+
+/// A body in `D`-dimensional space. The puzzle's moons are 3D.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Moon<const D: usize = 3> {
+    pub position: [isize; D],
+    pub velocity: [isize; D],
 }
 
 impl Moon {
     pub fn new(x: isize, y: isize, z: isize) -> Moon {
+        Moon::at([x, y, z])
+    }
+}
+
+impl<const D: usize> Moon<D> {
+    /// A moon at rest at `position`.
+    pub fn at(position: [isize; D]) -> Moon<D> {
         Moon {
-            position: na::Vector3::new(x, y, z),
-            velocity: na::Vector3::new(0, 0, 0),
+            position,
+            velocity: [0; D],
         }
     }
 
     pub fn kinetic_energy(&self) -> isize {
-        self.velocity.x.abs() + self.velocity.y.abs() + self.velocity.z.abs()
+        self.velocity.iter().map(|v| v.abs()).sum()
     }
 
     pub fn potential_energy(&self) -> isize {
-        self.position.x.abs() + self.position.y.abs() + self.position.z.abs()
+        self.position.iter().map(|p| p.abs()).sum()
     }
 
     pub fn total_energy(&self) -> isize {
@@ -45,168 +114,402 @@ impl Moon {
     }
 
     pub fn timestep(&mut self) {
-        self.position += self.velocity;
+        for (position, velocity) in self.position.iter_mut().zip(self.velocity.iter()) {
+            *position += velocity;
+        }
     }
 
-    pub fn gravity_for(&mut self, other: &Moon) {
-        if self.position.x > other.position.x {
-            self.velocity.x -= 1
-        } else if self.position.x < other.position.x {
-            self.velocity.x += 1
-        };
-
-        if self.position.y > other.position.y {
-            self.velocity.y -= 1
-        } else if self.position.y < other.position.y {
-            self.velocity.y += 1
-        };
-
-        if self.position.z > other.position.z {
-            self.velocity.z -= 1
-        } else if self.position.z < other.position.z {
-            self.velocity.z += 1
-        };
+    /// Pulls this moon one unit towards `other` along every axis where they
+    /// differ.
+    pub fn gravity_for(&mut self, other: &Moon<D>) {
+        for axis in 0..D {
+            self.velocity[axis] += (other.position[axis] - self.position[axis]).signum();
+        }
     }
 }
 
-impl fmt::Display for Moon {
+fn axis_name(axis: usize) -> String {
+    AXES.get(axis)
+        .map_or_else(|| format!("a{}", axis), |name| name.to_string())
+}
+
+impl<const D: usize> fmt::Display for Moon<D> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let vector = |values: &[isize; D]| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(axis, value)| format!("{}={}", axis_name(axis), value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
         write!(
             fmt,
-            "pos=<x={},y={},z={}>, vel=<x={}, y={}, z={}>",
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.velocity.x,
-            self.velocity.y,
-            self.velocity.z
+            "pos=<{}>, vel=<{}>",
+            vector(&self.position),
+            vector(&self.velocity)
         )
     }
 }
 
-fn simulate(mut moons: Vec<Moon>, steps: usize) -> isize {
-    for i in 0..steps {
-        println!("Step #{}", i);
-        for moon in &moons {
+/// One axis of a system of bodies: their positions and velocities along it,
+/// as parallel arrays.
+#[derive(Debug, Clone)]
+pub struct Axis {
+    pub positions: Vec<isize>,
+    pub velocities: Vec<isize>,
+    /// Body indices sorted by position. Kept between steps: bodies move
+    /// little, so re-sorting an almost sorted order is cheap.
+    order: Vec<usize>,
+}
+
+impl Axis {
+    pub fn new(positions: Vec<isize>, velocities: Vec<isize>) -> Axis {
+        let order = (0..positions.len()).collect();
+        Axis {
+            positions,
+            velocities,
+            order,
+        }
+    }
+
+    /// Applies gravity and then velocity along this axis.
+    ///
+    /// Every body is pulled by +1 for each body above it and -1 for each
+    /// body below it, so its velocity change is just the number of bodies
+    /// above minus the number below, read off its rank in the sorted order.
+    /// Bodies sharing a position, the body itself included, don't pull.
+    pub fn step(&mut self) {
+        let positions = &self.positions;
+        self.order.sort_by_key(|&body| positions[body]);
+
+        let n = self.order.len();
+        let mut below = 0;
+        while below < n {
+            let position = self.positions[self.order[below]];
+            let mut end = below + 1;
+            while end < n && self.positions[self.order[end]] == position {
+                end += 1;
+            }
+
+            let pull = (n - end) as isize - below as isize;
+            for &body in &self.order[below..end] {
+                self.velocities[body] += pull;
+            }
+            below = end;
+        }
+
+        for (position, velocity) in self.positions.iter_mut().zip(&self.velocities) {
+            *position += velocity;
+        }
+    }
+
+    /// The number of steps until this axis first returns to its current
+    /// state.
+    pub fn period(&self) -> usize {
+        let mut axis = self.clone();
+        let mut steps = 0;
+        loop {
+            axis.step();
+            steps += 1;
+            if axis.positions == self.positions && axis.velocities == self.velocities {
+                return steps;
+            }
+        }
+    }
+
+    /// The state `steps` steps from now. The axis comes back to where it
+    /// started after one period, so at most a period is ever simulated,
+    /// however large `steps` is.
+    pub fn after(&self, steps: u64) -> Axis {
+        let mut axis = self.clone();
+        let mut done = 0;
+        while done < steps {
+            axis.step();
+            done += 1;
+            if axis.positions == self.positions && axis.velocities == self.velocities {
+                for _ in 0..(steps - done) % done {
+                    axis.step();
+                }
+                break;
+            }
+        }
+
+        axis
+    }
+}
+
+/// A set of bodies stored axis by axis. Axes never influence each other, so
+/// each can be stepped on its own.
+#[derive(Debug, Clone)]
+pub struct System<const D: usize = 3> {
+    pub axes: [Axis; D],
+}
+
+impl<const D: usize> System<D> {
+    pub fn from_moons(moons: &[Moon<D>]) -> System<D> {
+        System {
+            axes: std::array::from_fn(|axis| {
+                Axis::new(
+                    moons.iter().map(|moon| moon.position[axis]).collect(),
+                    moons.iter().map(|moon| moon.velocity[axis]).collect(),
+                )
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.axes.first().map_or(0, |axis| axis.positions.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn moon(&self, body: usize) -> Moon<D> {
+        Moon {
+            position: std::array::from_fn(|axis| self.axes[axis].positions[body]),
+            velocity: std::array::from_fn(|axis| self.axes[axis].velocities[body]),
+        }
+    }
+
+    pub fn moons(&self) -> Vec<Moon<D>> {
+        (0..self.len()).map(|body| self.moon(body)).collect()
+    }
+
+    pub fn step(&mut self) {
+        for axis in self.axes.iter_mut() {
+            axis.step();
+        }
+    }
+
+    /// The system `steps` steps from now, see `Axis::after`.
+    pub fn after(&self, steps: u64) -> System<D> {
+        System {
+            axes: per_axis(&self.axes, |axis| axis.after(steps)),
+        }
+    }
+
+    pub fn total_energy(&self) -> isize {
+        (0..self.len())
+            .map(|body| self.moon(body).total_energy())
+            .sum()
+    }
+}
+
+/// Watches a simulation. Called once with step 0 before anything moves and
+/// then after every step.
+pub trait Observer<const D: usize> {
+    fn observe(&mut self, step: usize, system: &System<D>);
+}
+
+/// Prints every moon at every step, in the puzzle's format.
+pub struct Printer;
+
+impl<const D: usize> Observer<D> for Printer {
+    fn observe(&mut self, step: usize, system: &System<D>) {
+        println!("After {} steps:", step);
+        for moon in system.moons() {
             println!("{}", moon);
         }
-        step(&mut moons);
     }
+}
 
-    moons.iter().map(|moon| moon.total_energy()).sum()
+/// Records the total energy of the system at every step.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EnergyRecorder {
+    pub totals: Vec<(usize, isize)>,
 }
 
-fn step(moons: &mut Vec<Moon>) {
-    gravity(moons);
-    velocity(moons);
+impl EnergyRecorder {
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "step,total_energy")?;
+        for (step, total) in self.totals.iter() {
+            writeln!(out, "{},{}", step, total)?;
+        }
+        Ok(())
+    }
 }
 
-fn gravity(moons: &mut Vec<Moon>) {
-    let moons2 = moons.clone();
-    for moon in moons {
-        for othermoon in &moons2 {
-            if moon == othermoon {
-                continue;
-            }
+impl<const D: usize> Observer<D> for EnergyRecorder {
+    fn observe(&mut self, step: usize, system: &System<D>) {
+        self.totals.push((step, system.total_energy()));
+    }
+}
+
+/// Kinetic and potential energy of one moon at one step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoonEnergy {
+    pub step: usize,
+    pub moon: usize,
+    pub kinetic: isize,
+    pub potential: isize,
+}
+
+/// Records the kinetic and potential energy of every moon at every step.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MoonEnergyRecorder {
+    pub energies: Vec<MoonEnergy>,
+}
+
+impl MoonEnergyRecorder {
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "step,moon,kinetic,potential")?;
+        for energy in self.energies.iter() {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                energy.step, energy.moon, energy.kinetic, energy.potential
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<const D: usize> Observer<D> for MoonEnergyRecorder {
+    fn observe(&mut self, step: usize, system: &System<D>) {
+        for (moon, state) in system.moons().iter().enumerate() {
+            self.energies.push(MoonEnergy {
+                step,
+                moon,
+                kinetic: state.kinetic_energy(),
+                potential: state.potential_energy(),
+            });
+        }
+    }
+}
+
+/// Records where every moon is at every step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionRecorder<const D: usize = 3> {
+    /// `(step, moon, position)`
+    pub positions: Vec<(usize, usize, [isize; D])>,
+}
+
+impl<const D: usize> Default for PositionRecorder<D> {
+    fn default() -> Self {
+        PositionRecorder { positions: vec![] }
+    }
+}
 
-            moon.gravity_for(othermoon);
+impl<const D: usize> PositionRecorder<D> {
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let axes: Vec<String> = (0..D).map(axis_name).collect();
+        writeln!(out, "step,moon,{}", axes.join(","))?;
+        for (step, moon, position) in self.positions.iter() {
+            let position: Vec<String> = position.iter().map(isize::to_string).collect();
+            writeln!(out, "{},{},{}", step, moon, position.join(","))?;
         }
+        Ok(())
     }
 }
 
-fn velocity(moons: &mut Vec<Moon>) {
-    for moon in moons {
-        moon.timestep();
+impl<const D: usize> Observer<D> for PositionRecorder<D> {
+    fn observe(&mut self, step: usize, system: &System<D>) {
+        for (moon, state) in system.moons().iter().enumerate() {
+            self.positions.push((step, moon, state.position));
+        }
+    }
+}
+
+/// Runs `steps` steps and returns the total energy of the system.
+pub fn simulate<const D: usize>(moons: Vec<Moon<D>>, steps: usize) -> isize {
+    simulate_observed(moons, steps, &mut [])
+}
+
+/// Runs `steps` steps, showing every step to each of `observers`, and
+/// returns the total energy of the system.
+pub fn simulate_observed<const D: usize>(
+    moons: Vec<Moon<D>>,
+    steps: usize,
+    observers: &mut [&mut dyn Observer<D>],
+) -> isize {
+    let mut system = System::from_moons(&moons);
+    for step in 0..=steps {
+        if step > 0 {
+            system.step();
+        }
+        for observer in observers.iter_mut() {
+            observer.observe(step, &system);
+        }
     }
+
+    system.total_energy()
+}
+
+pub fn step<const D: usize>(moons: &mut [Moon<D>]) {
+    let mut system = System::from_moons(moons);
+    system.step();
+    moons.copy_from_slice(&system.moons());
 }
 
 #[aoc(day12, part1)]
-fn part_one(moons: &Vec<Moon>) -> String {
+fn part_one(moons: &[Moon]) -> String {
     let total_energy = simulate(moons.to_vec(), 1000);
     format!("{:?}", total_energy)
 }
 
-fn dimension_state(moons: &Vec<Moon>, dim: usize) -> [isize; 8] {
-    match dim {
-        0 => [
-            moons[0].position.x,
-            moons[0].velocity.x,
-            moons[1].position.x,
-            moons[1].velocity.x,
-            moons[2].position.x,
-            moons[2].velocity.x,
-            moons[3].position.x,
-            moons[3].velocity.x,
-        ],
-
-        1 => [
-            moons[0].position.y,
-            moons[0].velocity.y,
-            moons[1].position.y,
-            moons[1].velocity.y,
-            moons[2].position.y,
-            moons[2].velocity.y,
-            moons[3].position.y,
-            moons[3].velocity.y,
-        ],
-
-        2 => [
-            moons[0].position.z,
-            moons[0].velocity.z,
-            moons[1].position.z,
-            moons[1].velocity.z,
-            moons[2].position.z,
-            moons[2].velocity.z,
-            moons[3].position.z,
-            moons[3].velocity.z,
-        ],
-
-        _ => panic!(),
-    }
-}
-
-fn repeat(mut moons: Vec<Moon>) -> usize {
-    let init_state = [
-        dimension_state(&moons, 0),
-        dimension_state(&moons, 1),
-        dimension_state(&moons, 2)
-    ];
-
-    let mut cycle_len = [0; 3];
-
-
-    let mut step = 0;
-    while cycle_len[0] == 0 || cycle_len[1] == 0 || cycle_len[2] == 0 {
-        gravity(&mut moons);
-        velocity(&mut moons);
-        step += 1;
-
-        for dim in 0..3 {
-            if cycle_len[dim] == 0 && dimension_state(&moons, dim) == init_state[dim] {
-                cycle_len[dim] = step;
-            }
-        }
-    }
+/// Runs `work` on every axis, one thread each.
+fn per_axis<T, F, const D: usize>(axes: &[Axis; D], work: F) -> [T; D]
+where
+    T: Send,
+    F: Fn(&Axis) -> T + Sync,
+{
+    let work = &work;
+    let results: Vec<T> = thread::scope(|scope| {
+        let workers: Vec<_> = axes
+            .iter()
+            .map(|axis| scope.spawn(move || work(axis)))
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    let mut results = results.into_iter();
+    std::array::from_fn(|_| results.next().unwrap())
+}
 
-    lcm(cycle_len[0], lcm(cycle_len[1], cycle_len[2]))
+/// The number of steps after which each axis first returns to its starting
+/// state. The axes don't influence each other, so they are timed separately.
+pub fn axis_periods<const D: usize>(moons: Vec<Moon<D>>) -> [usize; D] {
+    per_axis(&System::from_moons(&moons).axes, Axis::period)
+}
 
+/// The moons after `steps` steps, found from each axis's cycle rather than
+/// by simulating that far, so `steps` can be as large as 10^15.
+pub fn state_at<const D: usize>(moons: &[Moon<D>], steps: u64) -> Vec<Moon<D>> {
+    System::from_moons(moons).after(steps).moons()
 }
 
-#[aoc(day12, part2)]
-fn part_two(input: &Vec<Moon>) -> String {
-    let mut moons = input.to_vec();
+/// The number of steps until every moon is back where it started, moving
+/// as it started: the least common multiple of the axis periods.
+pub fn repeat<const D: usize>(moons: Vec<Moon<D>>) -> usize {
+    axis_periods(moons)
+        .iter()
+        .fold(1, |period, &axis| lcm(period, axis))
+}
 
-    format!("{}", repeat(moons))
+#[aoc(day12, part2)]
+fn part_two(moons: &[Moon]) -> String {
+    format!("{}", repeat(moons.to_vec()))
 }
 
 #[cfg(test)]
 mod test {
-    use super::{generator_input, repeat, simulate};
+    use super::{
+        axis_periods, generator_input, lcm, parse_moons, repeat, simulate, simulate_observed,
+        state_at, step, EnergyRecorder, Moon, MoonEnergy, MoonEnergyRecorder, MoonParseError,
+        PositionRecorder, System,
+    };
 
     #[test]
     fn basic_test() {
         let input = "<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8, z=8>\n<x=3, y=5, z=-1>";
-        let moons = generator_input(input);
+        let moons = generator_input(input).unwrap();
         let total_energy = simulate(moons, 10);
         assert_eq!(total_energy, 179);
     }
@@ -214,7 +517,7 @@ mod test {
     #[test]
     fn bigger_test() {
         let input = "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>";
-        let moons = generator_input(input);
+        let moons = generator_input(input).unwrap();
         let total_energy = simulate(moons, 100);
         assert_eq!(total_energy, 1940);
     }
@@ -222,8 +525,236 @@ mod test {
     #[test]
     fn repeat_test() {
         let input = "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>";
-        let mut moons = generator_input(input);
+        let moons = generator_input(input).unwrap();
         let total_steps = repeat(moons);
         assert_eq!(total_steps, 4686774924);
     }
+
+    /// Every moon's position and velocity along one axis.
+    fn dimension_state<const D: usize>(moons: &[Moon<D>], dim: usize) -> Vec<(isize, isize)> {
+        moons
+            .iter()
+            .map(|moon| (moon.position[dim], moon.velocity[dim]))
+            .collect()
+    }
+
+    /// The puzzle's rule applied literally: every pair of distinct moons
+    /// pulls on each other.
+    fn pairwise_step(moons: &mut [Moon]) {
+        let before = moons.to_vec();
+        for (i, moon) in moons.iter_mut().enumerate() {
+            for (j, other) in before.iter().enumerate() {
+                if i != j {
+                    moon.gravity_for(other);
+                }
+            }
+            moon.timestep();
+        }
+    }
+
+    #[test]
+    fn test_system_matches_pairwise_gravity() {
+        // Plenty of bodies on a narrow range, so many share a coordinate
+        // (identical moons included) and the rank groups get exercised.
+        let mut seed: u64 = 12;
+        let mut coordinate = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as isize % 7 - 3
+        };
+        let mut moons: Vec<Moon> = (0..40)
+            .map(|_| Moon::new(coordinate(), coordinate(), coordinate()))
+            .collect();
+        moons.push(moons[0]);
+
+        let mut system = System::from_moons(&moons);
+        for _ in 0..200 {
+            pairwise_step(&mut moons);
+            system.step();
+            assert_eq!(system.moons(), moons);
+        }
+        assert_eq!(
+            system.total_energy(),
+            moons.iter().map(Moon::total_energy).sum::<isize>()
+        );
+    }
+
+    #[test]
+    fn test_recorders() {
+        let input = "<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8, z=8>\n<x=3, y=5, z=-1>";
+        let moons = generator_input(input).unwrap();
+
+        let mut energy = EnergyRecorder::default();
+        let mut moon_energy = MoonEnergyRecorder::default();
+        let mut positions = PositionRecorder::default();
+        let total = simulate_observed(
+            moons,
+            10,
+            &mut [&mut energy, &mut moon_energy, &mut positions],
+        );
+        assert_eq!(total, 179);
+
+        assert_eq!(energy.totals.len(), 11);
+        assert_eq!(energy.totals[0], (0, 0));
+        assert_eq!(energy.totals[10], (10, 179));
+        assert_eq!(
+            moon_energy.energies[40],
+            MoonEnergy {
+                step: 10,
+                moon: 0,
+                kinetic: 6,
+                potential: 6,
+            }
+        );
+        assert_eq!(positions.positions[4], (1, 0, [2, -1, 1]));
+
+        let mut csv = vec![];
+        positions.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().take(2).collect();
+        assert_eq!(lines, vec!["step,moon,x,y,z", "0,0,-1,0,2"]);
+
+        let mut csv = vec![];
+        energy.write_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().ends_with("\n10,179\n"));
+    }
+
+    #[test]
+    fn test_state_at() {
+        let input = "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>";
+        let moons = generator_input(input).unwrap();
+
+        // Around the shortest axis period (2028) as well, where x wraps around.
+        let mut state = moons.clone();
+        for steps in 0..2040 {
+            if !(50..2020).contains(&steps) {
+                assert_eq!(state_at(&moons, steps), state, "after {} steps", steps);
+            }
+            step(&mut state);
+        }
+
+        let full_cycle = 4686774924;
+        assert_eq!(state_at(&moons, full_cycle), moons);
+        assert_eq!(
+            state_at(&moons, 10u64.pow(15)),
+            state_at(&moons, 10u64.pow(15) % full_cycle)
+        );
+    }
+
+    /// Checks every axis period against direct simulation.
+    fn assert_axis_periods(moons: Vec<Moon>) {
+        let periods = axis_periods(moons.clone());
+        let longest = *periods.iter().max().unwrap();
+
+        let mut state = moons.clone();
+        for steps in 1..=longest {
+            step(&mut state);
+            for (dim, &period) in periods.iter().enumerate() {
+                let returned = dimension_state(&state, dim) == dimension_state(&moons, dim);
+                if steps < period {
+                    assert!(!returned, "axis {} returned early at {}", dim, steps);
+                } else if steps == period {
+                    assert!(returned, "axis {} did not return at {}", dim, steps);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_any_number_of_bodies() {
+        let cases = [
+            ("<x=-5, y=-3, z=-4>\n<x=2, y=0, z=1>", [12, 8, 8]),
+            (
+                "<x=-5, y=-3, z=-4>\n<x=2, y=0, z=1>\n<x=-2, y=3, z=-3>",
+                [8, 8, 8],
+            ),
+            (
+                "<x=-4, y=6, z=0>\n<x=-4, y=6, z=6>\n<x=-5, y=-4, z=3>\n<x=3, y=1, z=-4>\n\
+                 <x=-4, y=-6, z=-6>",
+                [281, 24, 24],
+            ),
+            (
+                "<x=-5, y=-3, z=-4>\n<x=2, y=0, z=1>\n<x=-2, y=3, z=-3>\n<x=5, y=-1, z=2>\n\
+                 <x=1, y=2, z=-2>\n<x=-3, y=-2, z=3>\n<x=4, y=1, z=-1>\n<x=0, y=-3, z=4>\n\
+                 <x=-4, y=0, z=0>\n<x=3, y=3, z=-4>",
+                [16, 4, 4],
+            ),
+        ];
+
+        for (input, periods) in cases.iter() {
+            let moons = generator_input(input).unwrap();
+            assert_eq!(axis_periods(moons.clone()), *periods);
+            assert_axis_periods(moons.clone());
+            assert_eq!(repeat(moons), lcm(periods[0], lcm(periods[1], periods[2])));
+        }
+    }
+
+    #[test]
+    fn test_two_bodies() {
+        // Two moons one apart on x swap places and come back in 4 steps.
+        let moons = generator_input("<x=0, y=0, z=0>\n<x=1, y=0, z=0>").unwrap();
+        assert_eq!(axis_periods(moons.clone()), [4, 1, 1]);
+        assert_eq!(repeat(moons), 4);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            generator_input("<x=1, y=2, z=3>\n<x=1, y=2>"),
+            Err(MoonParseError::Malformed("<x=1, y=2>".to_string()))
+        );
+        assert!(matches!(
+            generator_input("<x=1, y=two, z=3>"),
+            Err(MoonParseError::IntError(_))
+        ));
+        assert!(generator_input("x=1, y=2, z=3").is_err());
+    }
+
+    const BIGGER: &str =
+        "<x=-8, y=-10, z=0>\n<x=5, y=5, z=10>\n<x=2, y=-7, z=3>\n<x=9, y=-8, z=-3>";
+
+    #[test]
+    fn test_2d_is_a_projection_of_3d() {
+        let mut moons: Vec<Moon> = generator_input(BIGGER).unwrap();
+        let mut flat: Vec<Moon<2>> = moons
+            .iter()
+            .map(|moon| Moon::at([moon.position[0], moon.position[1]]))
+            .collect();
+
+        for _ in 0..100 {
+            step(&mut moons);
+            step(&mut flat);
+        }
+        for (moon, flat) in moons.iter().zip(&flat) {
+            assert_eq!(moon.position[..2], flat.position[..]);
+            assert_eq!(moon.velocity[..2], flat.velocity[..]);
+        }
+
+        let periods = axis_periods(generator_input(BIGGER).unwrap());
+        let flat: Vec<Moon<2>> =
+            parse_moons("<x=-8, y=-10>\n<x=5, y=5>\n<x=2, y=-7>\n<x=9, y=-8>").unwrap();
+        assert_eq!(axis_periods(flat.clone()), [periods[0], periods[1]]);
+        assert_eq!(repeat(flat), lcm(periods[0], periods[1]));
+    }
+
+    #[test]
+    fn test_4d() {
+        // A w axis copying x has x's period, so the full cycle is unchanged.
+        let moons: Vec<Moon<4>> = parse_moons(
+            "<x=-8, y=-10, z=0, w=-8>\n<x=5, y=5, z=10, w=5>\n\
+             <x=2, y=-7, z=3, w=2>\n<x=9, y=-8, z=-3, w=9>",
+        )
+        .unwrap();
+        let periods = axis_periods(moons.clone());
+        assert_eq!(periods[3], periods[0]);
+        assert_eq!(repeat(moons.clone()), 4686774924);
+
+        assert_eq!(
+            moons[0].to_string(),
+            "pos=<x=-8, y=-10, z=0, w=-8>, vel=<x=0, y=0, z=0, w=0>"
+        );
+        assert!(parse_moons::<4>("<x=1, y=2, z=3>").is_err());
+        assert!(parse_moons::<2>("<x=1, z=2>").is_err());
+    }
 }
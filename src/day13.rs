@@ -1,5 +1,8 @@
+use crate::grid::SparseGrid;
 use crate::intcode::{Action, Program};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, Read, Write};
 
 #[aoc_generator(day13)]
 fn generator_input(input: &str) -> Vec<i64> {
@@ -29,63 +32,348 @@ impl Tile {
             _ => panic!(),
         }
     }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '█',
+            Tile::Block => '#',
+            Tile::Paddle => '=',
+            Tile::Ball => 'o',
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Position(i64, i64);
 
+/// One `(x, y, tile)` output triple. The position `(-1, 0)` is not a tile but
+/// the segment display, and its third value is the current score.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Draw {
+    Tile(Position, Tile),
+    Score(i64),
+}
+
+/// Runs the cabinet until it has produced a full output triple. Returns the
+/// action that interrupted it otherwise: `Halt` or `AwaitingInput`.
+fn next_draw(program: &mut Program) -> Result<Draw, Action> {
+    let mut values = [0; 3];
+    for value in values.iter_mut() {
+        match program.execute() {
+            Action::Output(output) => *value = output,
+            action => return Err(action),
+        }
+    }
+
+    Ok(match values {
+        [-1, 0, score] => Draw::Score(score),
+        [x, y, tile_id] => Draw::Tile(Position(x, y), Tile::new(tile_id)),
+    })
+}
+
 fn draw_tiles(data: Vec<i64>) -> HashMap<Tile, Vec<Position>> {
     let mut program = Program::new(data, vec![]).halt_on_output();
     program.set_available_memory(3000);
     let mut tilemap: HashMap<Tile, Vec<Position>> = HashMap::new();
 
-    'main: loop {
-        let x_pos_action = program.execute();
-        if let Action::Halt = x_pos_action {
-            break 'main;
+    while let Ok(draw) = next_draw(&mut program) {
+        if let Draw::Tile(position, tile) = draw {
+            tilemap.entry(tile).or_default().push(position);
         }
+    }
+
+    tilemap
+}
+
+/// What the cabinet has drawn so far.
+#[derive(Debug, Clone, Default)]
+pub struct Screen {
+    pub tiles: SparseGrid<Tile>,
+    pub score: i64,
+}
+
+impl Screen {
+    pub fn apply(&mut self, draw: Draw) {
+        match draw {
+            Draw::Score(score) => self.score = score,
+            Draw::Tile(position, tile) => {
+                self.tiles.insert(position.0, position.1, tile);
+            }
+        }
+    }
+
+    pub fn count(&self, tile: &Tile) -> usize {
+        self.tiles.values().filter(|t| *t == tile).count()
+    }
+
+    pub fn find(&self, tile: &Tile) -> Option<Position> {
+        self.tiles
+            .iter()
+            .find(|(_, t)| *t == tile)
+            .map(|((x, y), _)| Position(x, y))
+    }
+}
 
-        let y_pos_action = program.execute();
-        let tile_id_action = program.execute();
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.tiles.render(|tile| tile.map_or(' ', Tile::to_char))
+        )?;
+        writeln!(f, "Score: {}", self.score)
+    }
+}
+
+/// Decides the joystick position (-1 left, 0 neutral, 1 right) each time the
+/// cabinet asks for one. `None` ends the session.
+pub trait Player {
+    fn joystick(&mut self, screen: &Screen) -> Option<i64>;
+}
+
+/// Keeps the paddle under the ball.
+pub struct AutoPlayer;
+
+impl Player for AutoPlayer {
+    fn joystick(&mut self, screen: &Screen) -> Option<i64> {
+        let ball = screen.find(&Tile::Ball)?;
+        let paddle = screen.find(&Tile::Paddle)?;
+        Some((ball.0 - paddle.0).signum())
+    }
+}
+
+/// Plays back a fixed list of joystick positions, for example the inputs of
+/// a recorded session.
+pub struct ScriptedPlayer(pub VecDeque<i64>);
+
+impl Player for ScriptedPlayer {
+    fn joystick(&mut self, _screen: &Screen) -> Option<i64> {
+        self.0.pop_front()
+    }
+}
+
+/// Reads keys from `input`: `a` or the left arrow moves left, `d` or the
+/// right arrow moves right, `s`, space or the down arrow stays put and `q`
+/// quits. Other keys are ignored. The terminal has to be out of canonical
+/// mode for keys to arrive without pressing enter.
+pub struct KeyboardPlayer<R: Read> {
+    input: R,
+    /// A key read after ESC that turned out not to start an arrow key.
+    pending: Option<u8>,
+}
+
+impl<R: Read> KeyboardPlayer<R> {
+    pub fn new(input: R) -> KeyboardPlayer<R> {
+        KeyboardPlayer {
+            input,
+            pending: None,
+        }
+    }
 
-        if let Action::Output(x_pos) = x_pos_action {
-            if let Action::Output(y_pos) = y_pos_action {
-                if let Action::Output(tile_id) = tile_id_action {
-                    let current_tiles_of_type = tilemap.entry(Tile::new(tile_id)).or_insert(vec![]);
-                    current_tiles_of_type.push(Position(x_pos, y_pos))
+    fn key(&mut self) -> Option<u8> {
+        if let Some(key) = self.pending.take() {
+            return Some(key);
+        }
+
+        let mut byte = [0];
+        match self.input.read(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            _ => None,
+        }
+    }
+}
+
+impl<R: Read> Player for KeyboardPlayer<R> {
+    fn joystick(&mut self, _screen: &Screen) -> Option<i64> {
+        loop {
+            match self.key()? {
+                b'a' => return Some(-1),
+                b'd' => return Some(1),
+                b's' | b' ' => return Some(0),
+                b'q' => return None,
+                // Arrow keys arrive as ESC [ A-D; anything else after ESC
+                // is read again as a key of its own.
+                0x1b => match self.key()? {
+                    b'[' => match self.key()? {
+                        b'D' => return Some(-1),
+                        b'C' => return Some(1),
+                        b'B' => return Some(0),
+                        _ => (),
+                    },
+                    key => self.pending = Some(key),
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Runs a cabinet, asking `player` for the joystick whenever the cabinet
+/// wants input, until it halts, no blocks are left or the player quits.
+/// `on_frame` sees the screen each time before the player does.
+pub fn run_session(
+    program: &mut Program,
+    player: &mut dyn Player,
+    on_frame: &mut dyn FnMut(&Screen),
+) -> Screen {
+    let mut screen = Screen::default();
+
+    loop {
+        match next_draw(program) {
+            Ok(draw) => screen.apply(draw),
+
+            Err(Action::AwaitingInput) => {
+                on_frame(&screen);
+                if screen.count(&Tile::Block) == 0 {
+                    break;
+                }
+                match player.joystick(&screen) {
+                    Some(joystick) => program.write_input(joystick),
+                    None => break,
                 }
             }
+
+            Err(_) => {
+                on_frame(&screen);
+                break;
+            }
         }
     }
 
-    tilemap
+    screen
+}
+
+/// Draws a frame over the previous one in an ANSI terminal.
+pub fn render_frame(out: &mut dyn Write, screen: &Screen) -> io::Result<()> {
+    write!(out, "\x1b[2J\x1b[H{}", screen)?;
+    out.flush()
+}
+
+/// Writes a frame as plain text with a numbered header, for frame dumps.
+pub fn dump_frame(out: &mut dyn Write, frame: usize, screen: &Screen) -> io::Result<()> {
+    writeln!(out, "--- frame {} ---", frame)?;
+    write!(out, "{}", screen)
 }
 
-fn play_game(data: Vec<i64>, tilemap: HashMap<Tile, Vec<Position>>) -> i64 {
+/// Plays the game on a running cabinet by keeping the paddle under the ball,
+/// until the cabinet halts or no blocks are left. Returns the last score.
+fn autoplay(mut program: Program) -> i64 {
+    run_session(&mut program, &mut AutoPlayer, &mut |_| ()).score
+}
+
+/// A cabinet with two quarters inserted, ready for `run_session`.
+pub fn insert_quarters(data: Vec<i64>) -> Program {
     let mut program = Program::new(data, vec![]).halt_on_output();
     program.set_available_memory(3000);
     program.data[0] = 2;
-    let mut score = 0;
-
-    'main: loop {
-    }
 
+    program
+}
 
-    score
+fn play_game(data: Vec<i64>) -> i64 {
+    autoplay(insert_quarters(data))
 }
 
 #[aoc(day13, part1)]
-fn part_one(data: &Vec<i64>) -> String {
+fn part_one(data: &[i64]) -> String {
     let tiles = draw_tiles(data.to_vec());
 
-
     format!("{}", tiles.get(&Tile::Block).unwrap().len())
 }
 
 #[aoc(day13, part2)]
-fn part_two(data: &Vec<i64>) -> String {
-    let tilemap = draw_tiles(data.to_vec());
-    let score = play_game(data.to_vec(), tilemap);
+fn part_two(data: &[i64]) -> String {
+    let score = play_game(data.to_vec());
 
     format!("{}", score)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        autoplay, dump_frame, run_session, KeyboardPlayer, Player, Screen, ScriptedPlayer,
+    };
+    use crate::intcode::compiler::compile;
+    use crate::intcode::Program;
+
+    /// A tiny cabinet: the block breaks once the paddle has been steered
+    /// under the ball, and the player has ten moves to get it there.
+    const CABINET: &str = "
+        fn draw(x, y, tile) { output(x); output(y); output(tile); }
+
+        fn main() {
+            draw(0, 0, 1);
+            draw(6, 0, 2);
+            let paddle = 1;
+            let ball = 3;
+            draw(paddle, 1, 3);
+            draw(ball, 0, 4);
+            let moves = 0;
+            while (paddle != ball) * (moves < 10) {
+                let joystick = input();
+                draw(paddle, 1, 0);
+                paddle = paddle + joystick;
+                draw(paddle, 1, 3);
+                moves = moves + 1;
+            }
+            if paddle == ball {
+                draw(6, 0, 0);
+                draw(-1, 0, 1234);
+                // Keep asking for input; the player should stop on its own.
+                while 1 { input(); }
+            }
+        }";
+
+    #[test]
+    fn test_autoplay() {
+        let program = Program::new(compile(CABINET).unwrap(), vec![]).halt_on_output();
+        assert_eq!(autoplay(program), 1234);
+    }
+
+    #[test]
+    fn test_frame_dump() {
+        let mut program = Program::new(compile(CABINET).unwrap(), vec![]).halt_on_output();
+        let mut player = ScriptedPlayer(vec![1, 1].into_iter().collect());
+        let mut dump = vec![];
+        let mut frame = 0;
+        let screen = run_session(&mut program, &mut player, &mut |screen| {
+            dump_frame(&mut dump, frame, screen).unwrap();
+            frame += 1;
+        });
+
+        assert_eq!(screen.score, 1234);
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            [
+                "--- frame 0 ---",
+                "█  o  #",
+                " =     ",
+                "Score: 0",
+                "--- frame 1 ---",
+                "█  o  #",
+                "  =    ",
+                "Score: 0",
+                "--- frame 2 ---",
+                "█  o   ",
+                "   =   ",
+                "Score: 1234",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_keyboard_player() {
+        let keys: &[u8] = b"xd\x1b[Ds\x1ba\x1b\x1b[Cq";
+        let mut player = KeyboardPlayer::new(keys);
+        let screen = Screen::default();
+        let moves: Vec<_> = (0..7).map(|_| player.joystick(&screen)).collect();
+
+        assert_eq!(
+            moves,
+            vec![Some(1), Some(-1), Some(0), Some(-1), Some(1), None, None]
+        );
+    }
+}
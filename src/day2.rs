@@ -1,4 +1,4 @@
-use crate::intcode::{Program};
+use crate::intcode::{search, Program};
 
 #[aoc_generator(day2)]
 fn generator_input(input: &str) -> Vec<i64> {
@@ -9,21 +9,28 @@ fn generator_input(input: &str) -> Vec<i64> {
 }
 
 #[aoc(day2, part1)]
-fn part_one(input: &Vec<i64>) -> i64 {
+fn part_one(input: &[i64]) -> i64 {
     solve(input.to_vec(), 12, 2)
 }
 
 #[aoc(day2, part2)]
-fn part_two(input: &Vec<i64>) -> i64 {
-    for noun in 0..100 {
-        for verb in 0..100 {
-            if solve(input.to_vec(), noun, verb) == 19_690_720 {
-                return 100 * noun + verb;
-            }
-        }
-    }
+fn part_two(input: &[i64]) -> i64 {
+    let base = Program::new(input.to_vec(), vec![]);
+    let candidates = (0..100).flat_map(|noun| (0..100).map(move |verb| (noun, verb)));
 
-    0
+    search::first(
+        &base,
+        candidates,
+        |program, &(noun, verb)| {
+            program.data[1] = noun;
+            program.data[2] = verb;
+        },
+        |mut program| {
+            program.execute();
+            program.data[0] == 19_690_720
+        },
+    )
+    .map_or(0, |(noun, verb)| 100 * noun + verb)
 }
 
 fn solve(input: Vec<i64>, noun: i64, verb: i64) -> i64 {
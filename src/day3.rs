@@ -1,3 +1,4 @@
+use crate::grid::{SparseGrid, YAxis};
 use std::num::ParseIntError;
 use std::str::FromStr;
 
@@ -12,10 +13,20 @@ pub enum Instruction {
 impl Instruction {
     pub fn get_distance(&self) -> u32 {
         match self {
-            Instruction::Right(x) => x.clone(),
-            Instruction::Left(x) => x.clone(),
-            Instruction::Down(x) => x.clone(),
-            Instruction::Up(x) => x.clone(),
+            Instruction::Right(x) => *x,
+            Instruction::Left(x) => *x,
+            Instruction::Down(x) => *x,
+            Instruction::Up(x) => *x,
+        }
+    }
+
+    /// The unit step taken along this instruction, with `y` pointing up.
+    pub fn delta(&self) -> (i64, i64) {
+        match self {
+            Instruction::Right(_) => (1, 0),
+            Instruction::Left(_) => (-1, 0),
+            Instruction::Up(_) => (0, 1),
+            Instruction::Down(_) => (0, -1),
         }
     }
 }
@@ -58,29 +69,292 @@ impl std::fmt::Display for InstructionParseError {
 
 impl std::error::Error for InstructionParseError {}
 
-pub struct Node {
-    position: (i32, i32),
-    steps: u32,
+/// A straight stretch of wire, with the number of steps the wire takes to
+/// reach its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub start: (i64, i64),
+    pub end: (i64, i64),
+    pub steps: u64,
 }
 
-impl std::hash::Hash for Node {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.position.hash(state);
+impl Segment {
+    /// Single points count as horizontal.
+    pub fn is_horizontal(&self) -> bool {
+        self.start.1 == self.end.1
+    }
+
+    pub fn len(&self) -> u64 {
+        ((self.end.0 - self.start.0).abs() + (self.end.1 - self.start.1).abs()) as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest and largest `x` on the segment.
+    fn x_range(&self) -> (i64, i64) {
+        (self.start.0.min(self.end.0), self.start.0.max(self.end.0))
+    }
+
+    /// The smallest and largest `y` on the segment.
+    fn y_range(&self) -> (i64, i64) {
+        (self.start.1.min(self.end.1), self.start.1.max(self.end.1))
+    }
+
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        let (min_x, max_x) = self.x_range();
+        let (min_y, max_y) = self.y_range();
+        (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y)
+    }
+
+    /// The steps the wire has taken when it gets to `point` along this
+    /// segment.
+    pub fn steps_to(&self, point: (i64, i64)) -> Option<u64> {
+        if !self.contains(point) {
+            return None;
+        }
+
+        let walked = (point.0 - self.start.0).abs() + (point.1 - self.start.1).abs();
+        Some(self.steps + walked as u64)
     }
 }
 
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.position == other.position
+/// A wire as the segments it is laid in, indexed so that crossings can be
+/// found without visiting every point on it.
+#[derive(Debug, Clone)]
+pub struct Wire {
+    segments: Vec<Segment>,
+    /// Indices of horizontal segments, sorted by `y` then by start.
+    horizontal: Vec<usize>,
+    /// Indices of vertical segments, sorted by `x` then by start.
+    vertical: Vec<usize>,
+}
+
+impl Wire {
+    /// Lays the wire out from the central port.
+    pub fn new(instructions: &[Instruction]) -> Wire {
+        let mut segments = vec![];
+        let mut start = (0, 0);
+        let mut steps = 0;
+        for instruction in instructions {
+            let (dx, dy) = instruction.delta();
+            let distance = i64::from(instruction.get_distance());
+            let end = (start.0 + dx * distance, start.1 + dy * distance);
+            if end != start {
+                segments.push(Segment { start, end, steps });
+            }
+            start = end;
+            steps += distance as u64;
+        }
+
+        let (mut horizontal, mut vertical): (Vec<usize>, Vec<usize>) =
+            (0..segments.len()).partition(|&i| segments[i].is_horizontal());
+        horizontal.sort_by_key(|&i| (segments[i].start.1, segments[i].x_range()));
+        vertical.sort_by_key(|&i| (segments[i].start.0, segments[i].y_range()));
+
+        Wire {
+            segments,
+            horizontal,
+            vertical,
+        }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The horizontal segments at height `y`, with their indices.
+    fn horizontal_at(&self, y: i64) -> impl Iterator<Item = (usize, &Segment)> + '_ {
+        self.horizontal_between(y, y)
+    }
+
+    /// The vertical segments at `x`, with their indices.
+    fn vertical_at(&self, x: i64) -> impl Iterator<Item = (usize, &Segment)> + '_ {
+        self.vertical_between(x, x)
+    }
+
+    /// The vertical segments with `x` between `min_x` and `max_x`, with their
+    /// indices.
+    fn vertical_between(
+        &self,
+        min_x: i64,
+        max_x: i64,
+    ) -> impl Iterator<Item = (usize, &Segment)> + '_ {
+        let from = self
+            .vertical
+            .partition_point(|&i| self.segments[i].start.0 < min_x);
+        self.vertical[from..]
+            .iter()
+            .map(move |&i| (i, &self.segments[i]))
+            .take_while(move |(_, segment)| segment.start.0 <= max_x)
+    }
+
+    /// The horizontal segments with `y` between `min_y` and `max_y`, with
+    /// their indices.
+    fn horizontal_between(
+        &self,
+        min_y: i64,
+        max_y: i64,
+    ) -> impl Iterator<Item = (usize, &Segment)> + '_ {
+        let from = self
+            .horizontal
+            .partition_point(|&i| self.segments[i].start.1 < min_y);
+        self.horizontal[from..]
+            .iter()
+            .map(move |&i| (i, &self.segments[i]))
+            .take_while(move |(_, segment)| segment.start.1 <= max_y)
+    }
+
+    /// Every number of steps after which the wire is at `point`, fewest
+    /// first. More than one when the wire crosses itself there.
+    pub fn visits(&self, point: (i64, i64)) -> Vec<u64> {
+        let mut visits: Vec<u64> = self
+            .horizontal_at(point.1)
+            .chain(self.vertical_at(point.0))
+            .filter_map(|(_, segment)| segment.steps_to(point))
+            .collect();
+        if point == (0, 0) {
+            visits.push(0);
+        }
+
+        // A corner is both the end of one segment and the start of the next.
+        visits.sort_unstable();
+        visits.dedup();
+        visits
+    }
+
+    /// The fewest steps the wire takes to reach `point`, if it ever does.
+    pub fn steps_to(&self, point: (i64, i64)) -> Option<u64> {
+        self.visits(point).first().cloned()
+    }
+
+    /// Where this wire and `other` cross, sorted and without the central
+    /// port. See `crossing_points` for stretches the wires share.
+    pub fn crossings(&self, other: &Wire) -> Vec<(i64, i64)> {
+        let mut points = self.crossing_points(other, |_, _, _| true);
+        points.retain(|&point| point != (0, 0));
+        points
+    }
+
+    /// Where the wire comes back to a point it has already been, sorted. Of
+    /// a stretch the wire runs along twice, only some points are listed, as
+    /// with `crossings`.
+    pub fn self_crossings(&self) -> Vec<(i64, i64)> {
+        self.crossing_points(self, |i, j, point| {
+            // Consecutive segments meet at their shared corner without the
+            // wire coming back to it.
+            i != j
+                && !(i + 1 == j && self.segments[i].end == point)
+                && !(j + 1 == i && self.segments[j].end == point)
+        })
+    }
+
+    /// Where a segment of this wire meets a segment of `other`, for the pairs
+    /// of segment indices and points that `keep` accepts. Sorted and
+    /// deduplicated.
+    ///
+    /// Where two segments run along each other every point they share is a
+    /// crossing, so only the points that can be closest under some `Metric`
+    /// are listed: both ends of the shared stretch, its point nearest the
+    /// port and where the two have walked equally far.
+    fn crossing_points<F>(&self, other: &Wire, keep: F) -> Vec<(i64, i64)>
+    where
+        F: Fn(usize, usize, (i64, i64)) -> bool,
+    {
+        let mut points = vec![];
+        let mut add = |i: usize, j: usize, point: (i64, i64)| {
+            if keep(i, j, point) {
+                points.push(point);
+            }
+        };
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            let (min_x, max_x) = segment.x_range();
+            let (min_y, max_y) = segment.y_range();
+            if segment.is_horizontal() {
+                let y = segment.start.1;
+                for (j, crossing) in other.vertical_between(min_x, max_x) {
+                    if crossing.contains((crossing.start.0, y)) {
+                        add(i, j, (crossing.start.0, y));
+                    }
+                }
+                for (j, parallel) in other.horizontal_at(y) {
+                    for point in shared_stretch(segment, parallel) {
+                        add(i, j, point);
+                    }
+                }
+            } else {
+                let x = segment.start.0;
+                for (j, crossing) in other.horizontal_between(min_y, max_y) {
+                    if crossing.contains((x, crossing.start.1)) {
+                        add(i, j, (x, crossing.start.1));
+                    }
+                }
+                for (j, parallel) in other.vertical_at(x) {
+                    for point in shared_stretch(segment, parallel) {
+                        add(i, j, point);
+                    }
+                }
+            }
+        }
+
+        points.sort_unstable();
+        points.dedup();
+        points
     }
 }
 
-impl Eq for Node {}
+/// The points worth checking where two segments on the same line overlap,
+/// see `Wire::crossing_points`, all clamped to the overlap. Empty if they
+/// don't actually overlap.
+fn shared_stretch(a: &Segment, b: &Segment) -> Vec<(i64, i64)> {
+    let horizontal = a.is_horizontal();
+    let span = |segment: &Segment| {
+        if horizontal {
+            (
+                segment.x_range(),
+                (segment.end.0 - segment.start.0).signum(),
+            )
+        } else {
+            (
+                segment.y_range(),
+                (segment.end.1 - segment.start.1).signum(),
+            )
+        }
+    };
+    let at = |coordinate: i64| {
+        if horizontal {
+            (coordinate, a.start.1)
+        } else {
+            (a.start.0, coordinate)
+        }
+    };
+
+    let (((a_from, a_to), a_slope), ((b_from, b_to), b_slope)) = (span(a), span(b));
+    let (from, to) = (a_from.max(b_from), a_to.min(b_to));
+    if from > to {
+        return vec![];
+    }
 
-impl Node {
-    fn new(position: (i32, i32), steps: u32) -> Self {
-        Node { position, steps }
+    let mut coordinates = vec![from, to, 0];
+    // Through the central port, which doesn't count as a crossing, the
+    // nearest crossings are the points either side of it.
+    if at(0.max(from).min(to)) == (0, 0) {
+        coordinates.extend(&[-1, 1]);
+    }
+    // Heading opposite ways, one wire's step count rises as fast as the
+    // other's falls, so they meet halfway across the gap between them.
+    if a_slope != b_slope {
+        let gap = b.steps_to(at(from)).unwrap() as i64 - a.steps_to(at(from)).unwrap() as i64;
+        let halfway = from + (gap * a_slope).div_euclid(2);
+        coordinates.extend(&[halfway, halfway + 1]);
     }
+
+    coordinates
+        .into_iter()
+        .map(|coordinate| at(coordinate.max(from).min(to)))
+        .collect()
 }
 
 #[aoc_generator(day3)]
@@ -88,86 +362,618 @@ fn generator_input(input: &str) -> Result<Vec<Vec<Instruction>>, InstructionPars
     input
         .lines()
         .map(|a| {
-            a.split(",")
-                .map(|i| Instruction::from_str(i))
+            a.split(',')
+                .map(Instruction::from_str)
                 .collect::<Result<Vec<Instruction>, InstructionParseError>>()
         })
         .collect()
 }
 
-struct CollisionChecker {
-    line_1: std::collections::HashSet<Node>,
-    line_2: std::collections::HashSet<Node>,
-    steps: u32,
-    current: (i32, i32),
+/// How close a collision is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Manhattan distance from the central port.
+    Manhattan,
+    /// Steps taken to get there, added up over the wires that do.
+    CombinedSteps,
+    /// The most steps any of the wires that get there takes.
+    MaxSteps,
+}
+
+impl Metric {
+    pub fn measure(&self, collision: &Collision) -> u64 {
+        let steps = collision.steps.iter().flatten();
+        match self {
+            Metric::Manhattan => (collision.position.0.abs() + collision.position.1.abs()) as u64,
+            Metric::CombinedSteps => steps.sum(),
+            Metric::MaxSteps => steps.max().cloned().unwrap_or(0),
+        }
+    }
+}
+
+/// A point where two or more wires pass, with the fewest steps each wire
+/// takes to get there, or `None` for wires that never do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub position: (i64, i64),
+    pub steps: Vec<Option<u64>>,
+}
+
+impl Collision {
+    /// How many different wires pass here.
+    pub fn wire_count(&self) -> usize {
+        self.steps.iter().flatten().count()
+    }
+}
+
+/// Where wires cross: a single point, or a straight stretch of two or more
+/// points that they run along together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossing {
+    Point((i64, i64)),
+    /// From `start` to `end`, both included, with `start` the lower or the
+    /// further left.
+    Stretch {
+        start: (i64, i64),
+        end: (i64, i64),
+    },
+}
+
+impl Crossing {
+    /// The first and last points of the crossing.
+    pub fn ends(&self) -> ((i64, i64), (i64, i64)) {
+        match *self {
+            Crossing::Point(point) => (point, point),
+            Crossing::Stretch { start, end } => (start, end),
+        }
+    }
+
+    /// How many points the crossing is made of.
+    pub fn point_count(&self) -> u64 {
+        let (start, end) = self.ends();
+        (end.0 - start.0 + end.1 - start.1) as u64 + 1
+    }
+
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        let (start, end) = self.ends();
+        (start.0..=end.0).contains(&x) && (start.1..=end.1).contains(&y)
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = (i64, i64)> {
+        let (start, end) = self.ends();
+        (start.0..=end.0).flat_map(move |x| (start.1..=end.1).map(move |y| (x, y)))
+    }
+}
+
+pub struct CollisionChecker {
+    wires: Vec<Wire>,
 }
+
 impl CollisionChecker {
-    pub fn new() -> Self {
+    pub fn new(wires: &[Vec<Instruction>]) -> Self {
         CollisionChecker {
-            line_1: Default::default(),
-            line_2: Default::default(),
-            steps: 0,
-            current: (0, 0),
+            wires: wires.iter().map(|wire| Wire::new(wire)).collect(),
         }
     }
 
-    pub fn follow_line(&mut self, instruction: &Instruction, line: usize) {
-        let dist = instruction.get_distance();
-        (0..dist).for_each(|_| {
-            match instruction {
-                &Instruction::Right(_) => self.current.0 += 1,
-                &Instruction::Left(_) => self.current.0 -= 1,
-                &Instruction::Up(_) => self.current.1 += 1,
-                &Instruction::Down(_) => self.current.1 -= 1,
+    pub fn wires(&self) -> &[Wire] {
+        &self.wires
+    }
+
+    /// The points worth checking for collisions, sorted by position: every
+    /// point where two different wires cross at an angle, and where wires
+    /// run along each other, the points of the shared stretch that can be
+    /// closest under some `Metric` (see `Wire::crossing_points`). Each comes
+    /// with the points either side of it, since a score jumps where another
+    /// wire joins or leaves a shared stretch. Only points at least two
+    /// different wires pass are kept; a wire crossing only itself doesn't
+    /// count.
+    ///
+    /// This is not every crossing: the rest of a shared stretch is left
+    /// out, however long it is. See `crossed_by` for those.
+    fn candidates(&self) -> Vec<Collision> {
+        let mut points = vec![];
+        for (i, wire) in self.wires.iter().enumerate() {
+            for other in self.wires[i + 1..].iter() {
+                for (x, y) in wire.crossings(other) {
+                    points.extend(&[(x, y), (x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+                }
             }
-            let position = self.current.clone();
-            self.steps += 1;
-            let to_add = Node::new(position, self.steps);
-            match line {
-                1 => self.line_1.insert(to_add),
-                2 => self.line_2.insert(to_add),
-                _ => unreachable!(),
-            };
-        })
+        }
+        points.retain(|&point| point != (0, 0));
+        points.sort_unstable();
+        points.dedup();
+
+        points
+            .into_iter()
+            .map(|position| Collision {
+                position,
+                steps: self
+                    .wires
+                    .iter()
+                    .map(|wire| wire.steps_to(position))
+                    .collect(),
+            })
+            .filter(|collision| collision.wire_count() >= 2)
+            .collect()
     }
 
-    pub fn collisions(&self) -> impl Iterator<Item = &Node> {
-        self.line_1.intersection(&self.line_2)
+    /// Every point where at least `k` different wires cross, apart from the
+    /// central port, sorted by position. Where the wires run along each
+    /// other the points come as whole stretches, and every point belongs to
+    /// exactly one `Crossing`: a vertical stretch is cut where it runs
+    /// through a horizontal one.
+    pub fn crossed_by(&self, k: usize) -> Vec<Crossing> {
+        let k = k.max(1);
+        let mut rows = vec![];
+        let mut columns = vec![];
+        let mut points = vec![];
+
+        for y in self.lines(true) {
+            for (from, to) in self.covered(true, y, k) {
+                if from == to {
+                    points.push((from, y));
+                } else {
+                    rows.push(Crossing::Stretch {
+                        start: (from, y),
+                        end: (to, y),
+                    });
+                }
+            }
+        }
+
+        for x in self.lines(false) {
+            for (from, to) in self.covered(false, x, k) {
+                let mut cuts: Vec<i64> = rows
+                    .iter()
+                    .map(Crossing::ends)
+                    .filter(|&(start, end)| {
+                        (start.0..=end.0).contains(&x) && (from..=to).contains(&start.1)
+                    })
+                    .map(|(start, _)| start.1)
+                    .collect();
+                cuts.sort_unstable();
+                cuts.push(to + 1);
+
+                let mut next = from;
+                for cut in cuts {
+                    if next == cut - 1 {
+                        points.push((x, next));
+                    } else if next < cut - 1 {
+                        columns.push(Crossing::Stretch {
+                            start: (x, next),
+                            end: (x, cut - 1),
+                        });
+                    }
+                    next = cut + 1;
+                }
+            }
+        }
+
+        // A point can turn up along both its row and its column.
+        points.sort_unstable();
+        points.dedup();
+        points.retain(|&point| !columns.iter().any(|column| column.contains(point)));
+
+        let mut crossings: Vec<Crossing> = rows
+            .into_iter()
+            .chain(columns)
+            .chain(points.into_iter().map(Crossing::Point))
+            .collect();
+        crossings.sort_unstable_by_key(Crossing::ends);
+        crossings
     }
 
-    pub fn restart(&mut self) {
-        self.current = (0, 0);
-        self.steps = 0;
+    /// Every point that all the wires cross, see `crossed_by`.
+    pub fn crossed_by_all(&self) -> Vec<Crossing> {
+        self.crossed_by(self.wires.len())
+    }
+
+    /// The rows (`horizontal`) or columns some wire runs along, sorted.
+    fn lines(&self, horizontal: bool) -> Vec<i64> {
+        let mut lines: Vec<i64> = self
+            .wires
+            .iter()
+            .flat_map(|wire| wire.segments())
+            .filter(|segment| segment.is_horizontal() == horizontal)
+            .map(|segment| {
+                if horizontal {
+                    segment.start.1
+                } else {
+                    segment.start.0
+                }
+            })
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
     }
-}
 
-fn populate_line(cc: &mut CollisionChecker, instructions: &Vec<Instruction>, line: usize) {
-    cc.restart();
-    instructions.iter().for_each(|i| cc.follow_line(i, line))
+    /// The runs of points along row `line` (`horizontal`) or column `line`
+    /// where at least `k` different wires are, leaving out the central
+    /// port. Each run is given as its first and last coordinate along the
+    /// line.
+    fn covered(&self, horizontal: bool, line: i64, k: usize) -> Vec<(i64, i64)> {
+        // Each wire covers the line from a coordinate up to the one before
+        // another: +1 where a range starts and -1 just after it ends.
+        let mut changes = vec![];
+        for (index, wire) in self.wires.iter().enumerate() {
+            for segment in wire.segments() {
+                let (along, across) = if horizontal {
+                    (segment.x_range(), segment.y_range())
+                } else {
+                    (segment.y_range(), segment.x_range())
+                };
+                if across.0 <= line && line <= across.1 {
+                    changes.push((along.0, index, 1));
+                    changes.push((along.1 + 1, index, -1));
+                }
+            }
+        }
+        changes.sort_unstable();
+
+        let mut depth = vec![0; self.wires.len()];
+        let mut covering = 0;
+        let mut runs: Vec<(i64, i64)> = vec![];
+        let mut i = 0;
+        while i < changes.len() {
+            let at = changes[i].0;
+            while i < changes.len() && changes[i].0 == at {
+                let (_, index, change) = changes[i];
+                let before = depth[index];
+                depth[index] += change;
+                match (before, depth[index]) {
+                    (0, _) => covering += 1,
+                    (_, 0) => covering -= 1,
+                    _ => (),
+                }
+                i += 1;
+            }
+
+            if covering >= k && i < changes.len() {
+                let until = changes[i].0 - 1;
+                match runs.last_mut() {
+                    Some((_, end)) if *end + 1 == at => *end = until,
+                    _ => runs.push((at, until)),
+                }
+            }
+        }
+
+        if line == 0 {
+            runs = runs
+                .into_iter()
+                .flat_map(|(from, to)| {
+                    if from <= 0 && 0 <= to {
+                        vec![(from, -1), (1, to)]
+                    } else {
+                        vec![(from, to)]
+                    }
+                })
+                .filter(|(from, to)| from <= to)
+                .collect();
+        }
+
+        runs
+    }
+
+    /// The closest point under `metric` where at least `k` wires cross.
+    /// The best score is always found among the candidates: between them a
+    /// score changes steadily along a stretch.
+    pub fn closest(&self, k: usize, metric: Metric) -> Option<Collision> {
+        self.candidates()
+            .into_iter()
+            .filter(|collision| collision.wire_count() >= k)
+            .min_by_key(|collision| metric.measure(collision))
+    }
 }
 
 #[aoc(day3, part1)]
-fn part_one(input: &[Vec<Instruction>]) -> i32 {
-    let mut cc = CollisionChecker::new();
-    populate_line(&mut cc, &input[0], 1);
-    populate_line(&mut cc, &input[1], 2);
-    cc.collisions()
-        .map(|c| c.position.0.abs() + c.position.1.abs())
-        .min()
-        .expect("Failed to find min")
+fn part_one(input: &[Vec<Instruction>]) -> u64 {
+    let closest = CollisionChecker::new(input)
+        .closest(2, Metric::Manhattan)
+        .expect("Failed to find min");
+    Metric::Manhattan.measure(&closest)
 }
 
 #[aoc(day3, part2)]
-fn part_two(input: &[Vec<Instruction>]) -> u32 {
-    let mut cc = CollisionChecker::new();
-    populate_line(&mut cc, &input[0], 1);
-    populate_line(&mut cc, &input[1], 2);
-    cc.collisions()
-        .map(|c| {
-            let d1 = cc.line_1.get(c).unwrap().steps;
-            let d2 = cc.line_2.get(c).unwrap().steps;
-            d1 + d2
-        })
-        .min()
-        .expect("Failed to find min")
+fn part_two(input: &[Vec<Instruction>]) -> u64 {
+    let closest = CollisionChecker::new(input)
+        .closest(2, Metric::CombinedSteps)
+        .expect("Failed to find min");
+    Metric::CombinedSteps.measure(&closest)
+}
+
+/// Draws the wires the way the puzzle does: `-` and `|` along a wire, `+`
+/// where it turns, `X` where different wires cross and `o` at the central
+/// port.
+pub fn plot(wires: &[Vec<Instruction>]) -> SparseGrid<char> {
+    let mut grid = SparseGrid::new(YAxis::Up);
+    let mut owners: SparseGrid<usize> = SparseGrid::new(YAxis::Up);
+
+    for (wire, instructions) in wires.iter().enumerate() {
+        let (mut x, mut y) = (0, 0);
+        for (index, instruction) in instructions.iter().enumerate() {
+            let (dx, dy) = instruction.delta();
+            for step in 1..=instruction.get_distance() {
+                x += dx;
+                y += dy;
+                let turns = step == instruction.get_distance() && index + 1 < instructions.len();
+                let glyph = match (turns, dx) {
+                    (true, _) => '+',
+                    (false, 0) => '|',
+                    (false, _) => '-',
+                };
+
+                match owners.get(x, y) {
+                    Some(&owner) if owner != wire => {
+                        grid.insert(x, y, 'X');
+                    }
+                    _ => {
+                        owners.insert(x, y, wire);
+                        grid.insert(x, y, glyph);
+                    }
+                }
+            }
+        }
+    }
+    grid.insert(0, 0, 'o');
+
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generator_input, part_one, part_two, plot, Collision, CollisionChecker, Crossing,
+        Instruction, Metric, Wire,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_plot() {
+        let wires = generator_input("R8,U5,L5,D3\nU7,R6,D4,L4").unwrap();
+        let expected = "\
++-----+..
+|.....|..
+|..+--X-+
+|..|..|.|
+|.-X--+.|
+|..|....|
+|.......|
+o-------+
+";
+
+        let drawn = plot(&wires).render(|c| *c.unwrap_or(&'.')).to_string();
+        assert_eq!(drawn, expected);
+    }
+
+    #[test]
+    fn test_examples() {
+        let examples = [
+            ("R8,U5,L5,D3\nU7,R6,D4,L4", 6, 30),
+            (
+                "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83",
+                159,
+                610,
+            ),
+            (
+                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+                135,
+                410,
+            ),
+        ];
+        for &(input, distance, steps) in examples.iter() {
+            let wires = generator_input(input).unwrap();
+            assert_eq!(part_one(&wires), distance, "{}", input);
+            assert_eq!(part_two(&wires), steps, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_long_and_overlapping_wires() {
+        let wires = generator_input("R8\nU1,R2,D1,R6").unwrap();
+        assert_eq!(part_one(&wires), 2);
+        assert_eq!(part_two(&wires), 6);
+
+        // Leaving the port the same way, the wires cross right next to it.
+        let wires = generator_input("R5\nR3").unwrap();
+        assert_eq!(part_one(&wires), 1);
+        assert_eq!(part_two(&wires), 2);
+        let wires = generator_input("U2,L1,D4\nR1,U1,L1,D3").unwrap();
+        assert_eq!(part_one(&wires), 1);
+
+        let wires = generator_input("R1000000000,U1000000000\nU1000000000,R1000000000").unwrap();
+        let collisions = CollisionChecker::new(&wires).candidates();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].position, (1_000_000_000, 1_000_000_000));
+        assert_eq!(
+            collisions[0].steps,
+            [Some(2_000_000_000), Some(2_000_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_many_wires() {
+        let wires = generator_input("R8,U5,L5,D3\nU7,R6,D4,L4\nU3,R10").unwrap();
+        let checker = CollisionChecker::new(&wires);
+
+        // The second and third wires share x = 0 up to y = 3, and y = 3
+        // from x = 2 to x = 6.
+        assert_eq!(
+            checker.crossed_by(2),
+            [
+                Crossing::Stretch {
+                    start: (0, 1),
+                    end: (0, 3)
+                },
+                Crossing::Stretch {
+                    start: (2, 3),
+                    end: (6, 3)
+                },
+                Crossing::Point((6, 5)),
+                Crossing::Point((8, 3)),
+            ]
+        );
+        let points: u64 = checker
+            .crossed_by(2)
+            .iter()
+            .map(Crossing::point_count)
+            .sum();
+        assert_eq!(points, 10);
+        assert_eq!(checker.crossed_by(3), [Crossing::Point((3, 3))]);
+        assert_eq!(checker.crossed_by_all(), checker.crossed_by(3));
+
+        let collision = checker.closest(3, Metric::MaxSteps).unwrap();
+        assert_eq!(collision.steps, [Some(20), Some(20), Some(6)]);
+        assert_eq!(Metric::Manhattan.measure(&collision), 6);
+        assert_eq!(Metric::CombinedSteps.measure(&collision), 46);
+        assert_eq!(Metric::MaxSteps.measure(&collision), 20);
+
+        let collision = checker.closest(2, Metric::Manhattan).unwrap();
+        assert_eq!(collision.position, (0, 1));
+        assert_eq!(collision.steps, [None, Some(1), Some(1)]);
+        assert_eq!(collision.wire_count(), 2);
+    }
+
+    #[test]
+    fn test_self_crossings() {
+        let wires = generator_input("R4,U2,L2,D4\nU1,R5").unwrap();
+        let wire = Wire::new(&wires[0]);
+        assert_eq!(wire.self_crossings(), [(2, 0)]);
+        assert_eq!(wire.visits((2, 0)), [2, 10]);
+
+        // The second wire meets the first at (2, 1) on its way down, but
+        // sooner at (4, 1) on its way up.
+        let checker = CollisionChecker::new(&wires);
+        let collision = checker.closest(2, Metric::CombinedSteps).unwrap();
+        assert_eq!(collision.position, (4, 1));
+        assert_eq!(collision.steps, [Some(5), Some(5)]);
+
+        // Doubling back revisits the whole stretch up to the turn, of which
+        // only the port and the point next to it are listed.
+        let wire = Wire::new(&generator_input("R5,L8").unwrap()[0]);
+        assert_eq!(wire.self_crossings(), [(0, 0), (1, 0)]);
+        assert_eq!(wire.visits((1, 0)), [1, 9]);
+    }
+
+    #[test]
+    fn test_max_steps_along_a_shared_stretch() {
+        // The wires run along y = 0 in opposite directions, having walked
+        // 8 + x and 22 - x steps, so they are level at x = 7.
+        let wires = generator_input("D1,L3,U1,R13\nU1,R10,D1,L10").unwrap();
+        let checker = CollisionChecker::new(&wires);
+        let collision = checker.closest(2, Metric::MaxSteps).unwrap();
+        assert_eq!(collision.position, (7, 0));
+        assert_eq!(collision.steps, [Some(15), Some(15)]);
+    }
+
+    #[test]
+    fn test_third_wire_on_a_shared_stretch() {
+        // The first two wires share y = 3 from x = 1 to x = 6, and the third
+        // crosses it at x = 1, pushing the score up right at the end of the
+        // stretch.
+        let wires = generator_input("R1,U3,R5,R5\nU3,R4,U1,U2\nL2,U3,R3").unwrap();
+        let collision = CollisionChecker::new(&wires)
+            .closest(2, Metric::MaxSteps)
+            .unwrap();
+        assert_eq!(collision.position, (2, 3));
+        assert_eq!(Metric::MaxSteps.measure(&collision), 5);
+    }
+
+    /// The fewest steps each wire takes to every point it passes, walking
+    /// one step at a time.
+    fn walk(wires: &[Vec<Instruction>]) -> HashMap<(i64, i64), Vec<Option<u64>>> {
+        let mut points = HashMap::new();
+        for (wire, instructions) in wires.iter().enumerate() {
+            let (mut position, mut steps) = ((0, 0), 0);
+            for instruction in instructions {
+                let (dx, dy) = instruction.delta();
+                for _ in 0..instruction.get_distance() {
+                    position = (position.0 + dx, position.1 + dy);
+                    steps += 1;
+                    let seen = points
+                        .entry(position)
+                        .or_insert_with(|| vec![None; wires.len()]);
+                    seen[wire] = Some(seen[wire].map_or(steps, |first: u64| first.min(steps)));
+                }
+            }
+        }
+
+        points
+    }
+
+    #[test]
+    fn test_against_walking_every_step() {
+        let mut seed: u64 = 3;
+        let mut random = |below: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % below
+        };
+
+        for _ in 0..500 {
+            let wires: Vec<Vec<Instruction>> = (0..3)
+                .map(|_| {
+                    (0..2 + random(5))
+                        .map(|_| {
+                            let distance = 1 + random(4) as u32;
+                            match random(4) {
+                                0 => Instruction::Right(distance),
+                                1 => Instruction::Left(distance),
+                                2 => Instruction::Up(distance),
+                                _ => Instruction::Down(distance),
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            let walked = walk(&wires);
+            let checker = CollisionChecker::new(&wires);
+
+            for collision in checker.candidates() {
+                assert_eq!(Some(&collision.steps), walked.get(&collision.position));
+            }
+            for k in 2..=3 {
+                let mut expected: Vec<(i64, i64)> = walked
+                    .iter()
+                    .filter(|(&position, steps)| {
+                        position != (0, 0) && steps.iter().flatten().count() >= k
+                    })
+                    .map(|(&position, _)| position)
+                    .collect();
+                expected.sort_unstable();
+                let mut found: Vec<(i64, i64)> = checker
+                    .crossed_by(k)
+                    .iter()
+                    .flat_map(Crossing::points)
+                    .collect();
+                found.sort_unstable();
+                assert_eq!(found, expected, "{} wires in {:?}", k, wires);
+
+                for &metric in [Metric::Manhattan, Metric::CombinedSteps, Metric::MaxSteps].iter() {
+                    let expected = walked
+                        .iter()
+                        .filter(|(&position, steps)| {
+                            position != (0, 0) && steps.iter().flatten().count() >= k
+                        })
+                        .map(|(&position, steps)| {
+                            metric.measure(&Collision {
+                                position,
+                                steps: steps.clone(),
+                            })
+                        })
+                        .min();
+                    let found = checker
+                        .closest(k, metric)
+                        .map(|collision| metric.measure(&collision));
+                    assert_eq!(
+                        found, expected,
+                        "{:?} of {} wires in {:?}",
+                        metric, k, wires
+                    );
+                }
+            }
+        }
+    }
 }
@@ -1,12 +1,13 @@
-use pathfinding::directed::bfs::bfs;
+use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
 
+/// The body everything orbits unless a map says otherwise.
+pub const CENTER_OF_MASS: &str = "COM";
+
 #[aoc_generator(day6)]
-fn generator_input(input: &str) -> Vec<OrbitRelation> {
-    input
-        .lines()
-        .map(|s| OrbitRelation::from_str(s).unwrap())
-        .collect()
+fn generator_input(input: &str) -> Result<OrbitMap, InvalidOrbitMap> {
+    OrbitMap::parse(input)
 }
 
 pub struct OrbitRelation {
@@ -15,68 +16,693 @@ pub struct OrbitRelation {
 }
 
 impl FromStr for OrbitRelation {
-    type Err = std::convert::Infallible;
+    type Err = OrbitError;
 
+    /// Parses a single `PARENT)BODY` line, reported as line 1 on failure.
     fn from_str(s: &str) -> Result<OrbitRelation, Self::Err> {
-        let parts: Vec<&str> = s.split(")").collect();
-        Ok(OrbitRelation {
-            parent: parts[0].into(),
-            identity: parts[1].into(),
-        })
+        let mut parts = s.trim().split(')');
+        let parent = parts.next().filter(|part| !part.is_empty());
+        let identity = parts.next().filter(|part| !part.is_empty());
+        match (parent, identity, parts.next()) {
+            (Some(parent), Some(identity), None) => Ok(OrbitRelation {
+                parent: parent.into(),
+                identity: identity.into(),
+            }),
+            _ => Err(OrbitError::Malformed {
+                line: 1,
+                text: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Something wrong with an orbit map. Lines are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrbitError {
+    /// A line that isn't `PARENT)BODY`.
+    Malformed { line: usize, text: String },
+    /// A body given a second parent on `line`.
+    MultipleParents {
+        body: String,
+        first_line: usize,
+        line: usize,
+    },
+    /// Bodies orbiting each other in a loop, each with the line giving its
+    /// parent.
+    Cycle {
+        bodies: Vec<String>,
+        lines: Vec<usize>,
+    },
+    /// The root does not appear in the map.
+    MissingRoot(String),
+    /// The root is given a parent on `line`.
+    RootHasParent { root: String, line: usize },
+    /// A body that orbits nothing and is not the root, so neither it nor
+    /// anything orbiting it is connected to the root. `line` is where it
+    /// first appears.
+    Disconnected { body: String, line: usize },
+}
+
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitError::Malformed { line, text } => {
+                write!(f, "Line {}: expected PARENT)BODY, found {:?}", line, text)
+            }
+            OrbitError::MultipleParents {
+                body,
+                first_line,
+                line,
+            } => write!(
+                f,
+                "Line {}: {} already orbits something since line {}",
+                line, body, first_line
+            ),
+            OrbitError::Cycle { bodies, lines } => {
+                let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+                write!(
+                    f,
+                    "Lines {}: {} orbit each other in a cycle",
+                    lines.join(", "),
+                    bodies.join(", ")
+                )
+            }
+            OrbitError::MissingRoot(root) => write!(f, "Root {} is not in the map", root),
+            OrbitError::RootHasParent { root, line } => {
+                write!(f, "Line {}: root {} orbits something", line, root)
+            }
+            OrbitError::Disconnected { body, line } => write!(
+                f,
+                "Line {}: {} orbits nothing and is not connected to the root",
+                line, body
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OrbitError {}
+
+/// Every problem found while validating an orbit map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidOrbitMap(pub Vec<OrbitError>);
+
+impl fmt::Display for InvalidOrbitMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for error in self.0.iter() {
+            writeln!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidOrbitMap {}
+
+/// Index of a body in an `OrbitMap`.
+pub type BodyId = usize;
+
+/// Every body and what it orbits, with bodies interned as `BodyId`s so
+/// queries don't have to hash names.
+#[derive(Debug, Clone, Default)]
+pub struct OrbitMap {
+    names: Vec<String>,
+    ids: HashMap<String, BodyId>,
+    parents: Vec<Option<BodyId>>,
+    children: Vec<Vec<BodyId>>,
+    depths: Vec<usize>,
+}
+
+impl OrbitMap {
+    /// Parses a map of `PARENT)BODY` lines centred on `COM`.
+    pub fn parse(input: &str) -> Result<OrbitMap, InvalidOrbitMap> {
+        OrbitMap::parse_with_root(input, CENTER_OF_MASS)
+    }
+
+    /// Parses a map that must form a single tree around `root`, reporting
+    /// every problem found rather than just the first. Blank lines are
+    /// skipped.
+    pub fn parse_with_root(input: &str, root: &str) -> Result<OrbitMap, InvalidOrbitMap> {
+        let mut map = OrbitMap::default();
+        let mut errors = vec![];
+        // Where each body first appears and where it was given its parent.
+        let mut first_lines: Vec<usize> = vec![];
+        let mut parent_lines: Vec<Option<usize>> = vec![];
+
+        for (index, text) in input.lines().enumerate() {
+            let line = index + 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let relation = match OrbitRelation::from_str(text) {
+                Ok(relation) => relation,
+                Err(_) => {
+                    errors.push(OrbitError::Malformed {
+                        line,
+                        text: text.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let parent = map.intern(&relation.parent);
+            let identity = map.intern(&relation.identity);
+            first_lines.resize(map.len(), line);
+            parent_lines.resize(map.len(), None);
+
+            if let Some(first_line) = parent_lines[identity] {
+                errors.push(OrbitError::MultipleParents {
+                    body: relation.identity,
+                    first_line,
+                    line,
+                });
+                continue;
+            }
+            parent_lines[identity] = Some(line);
+            map.parents[identity] = Some(parent);
+            map.children[parent].push(identity);
+        }
+
+        match map.id(root) {
+            None => errors.push(OrbitError::MissingRoot(root.to_string())),
+            Some(id) => {
+                if let Some(line) = parent_lines[id] {
+                    errors.push(OrbitError::RootHasParent {
+                        root: root.to_string(),
+                        line,
+                    });
+                }
+                for body in map.roots().filter(|&body| body != id) {
+                    errors.push(OrbitError::Disconnected {
+                        body: map.name(body).to_string(),
+                        line: first_lines[body],
+                    });
+                }
+            }
+        }
+
+        let reached = map.compute_depths();
+        for cycle in map.cycles(&reached) {
+            errors.push(OrbitError::Cycle {
+                bodies: cycle
+                    .iter()
+                    .map(|&body| map.name(body).to_string())
+                    .collect(),
+                lines: cycle
+                    .iter()
+                    .filter_map(|&body| parent_lines[body])
+                    .collect(),
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(map)
+        } else {
+            Err(InvalidOrbitMap(errors))
+        }
+    }
+
+    /// Fills in every depth, outwards from the bodies that orbit nothing so
+    /// every parent has its depth before its children need it. Returns which
+    /// bodies were reached: the others sit on or hang off a cycle.
+    fn compute_depths(&mut self) -> Vec<bool> {
+        let mut reached = vec![false; self.len()];
+        let mut pending: Vec<BodyId> = self.roots().collect();
+        while let Some(body) = pending.pop() {
+            reached[body] = true;
+            for &child in self.children[body].iter() {
+                self.depths[child] = self.depths[body] + 1;
+                pending.push(child);
+            }
+        }
+
+        reached
+    }
+
+    /// The cycles among the bodies not `reached` from a root. Every body
+    /// has at most one parent, so following parents from any such body ends
+    /// up going round exactly one cycle.
+    fn cycles(&self, reached: &[bool]) -> Vec<Vec<BodyId>> {
+        // The walk each body was first seen on.
+        let mut walks: Vec<Option<usize>> = vec![None; self.len()];
+        let mut cycles = vec![];
+
+        for start in (0..self.len()).filter(|&body| !reached[body]) {
+            let mut body = start;
+            while walks[body].is_none() {
+                walks[body] = Some(start);
+                body = self.parents[body].expect("Unreached bodies have parents");
+            }
+            if walks[body] != Some(start) {
+                continue;
+            }
+
+            let mut cycle = vec![body];
+            let mut next = self.parents[body].unwrap();
+            while next != body {
+                cycle.push(next);
+                next = self.parents[next].unwrap();
+            }
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    fn intern(&mut self, name: &str) -> BodyId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.parents.push(None);
+        self.children.push(vec![]);
+        self.depths.push(0);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<BodyId> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn name(&self, body: BodyId) -> &str {
+        &self.names[body]
+    }
+
+    /// The bodies that orbit nothing.
+    pub fn roots(&self) -> impl Iterator<Item = BodyId> + '_ {
+        (0..self.len()).filter(move |&body| self.parents[body].is_none())
+    }
+
+    /// The body `body` directly orbits.
+    pub fn parent(&self, body: BodyId) -> Option<BodyId> {
+        self.parents[body]
+    }
+
+    /// The bodies directly orbiting `body`.
+    pub fn children(&self, body: BodyId) -> &[BodyId] {
+        &self.children[body]
+    }
+
+    /// The number of bodies `body` orbits, directly or indirectly.
+    pub fn depth(&self, body: BodyId) -> usize {
+        self.depths[body]
+    }
+
+    /// Everything `body` orbits, nearest first.
+    pub fn ancestors(&self, body: BodyId) -> impl Iterator<Item = BodyId> + '_ {
+        std::iter::successors(self.parent(body), move |&body| self.parent(body))
+    }
+
+    /// The deepest body that both `a` and `b` are or orbit, or `None` if they
+    /// are in separate systems.
+    pub fn lowest_common_ancestor(&self, mut a: BodyId, mut b: BodyId) -> Option<BodyId> {
+        while self.depth(a) > self.depth(b) {
+            a = self.parent(a)?;
+        }
+        while self.depth(b) > self.depth(a) {
+            b = self.parent(b)?;
+        }
+        while a != b {
+            a = self.parent(a)?;
+            b = self.parent(b)?;
+        }
+
+        Some(a)
+    }
+
+    /// The bodies from `a` up to their common ancestor and down to `b`, both
+    /// ends included.
+    pub fn path(&self, a: BodyId, b: BodyId) -> Option<Vec<BodyId>> {
+        let common = self.lowest_common_ancestor(a, b)?;
+        let up = std::iter::once(a).chain(self.ancestors(a));
+        let down = std::iter::once(b).chain(self.ancestors(b));
+
+        let mut path: Vec<BodyId> = up.take_while(|&body| body != common).collect();
+        path.push(common);
+        let mut down: Vec<BodyId> = down.take_while(|&body| body != common).collect();
+        down.reverse();
+        path.extend(down);
+
+        Some(path)
+    }
+
+    /// The number of orbits between `a` and `b`.
+    pub fn distance(&self, a: BodyId, b: BodyId) -> Option<usize> {
+        let common = self.lowest_common_ancestor(a, b)?;
+        Some(self.depth(a) + self.depth(b) - 2 * self.depth(common))
+    }
+
+    /// The number of orbital transfers needed to move from whatever `a`
+    /// orbits to whatever `b` orbits.
+    pub fn transfers(&self, a: BodyId, b: BodyId) -> Option<usize> {
+        self.distance(self.parent(a)?, self.parent(b)?)
+    }
+
+    pub fn direct_orbits(&self) -> usize {
+        self.parents
+            .iter()
+            .filter(|parent| parent.is_some())
+            .count()
+    }
+
+    /// Direct and indirect orbits together: each body orbits as many bodies
+    /// as its depth.
+    pub fn total_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    pub fn indirect_orbits(&self) -> usize {
+        self.total_orbits() - self.direct_orbits()
+    }
+
+    /// Every body, each before the bodies orbiting it and those in the
+    /// order they were listed. Walks with an explicit stack, so deep maps
+    /// are fine.
+    pub fn preorder(&self) -> Vec<BodyId> {
+        let mut order = Vec::with_capacity(self.len());
+        let mut pending: Vec<BodyId> = self.roots().collect();
+        pending.reverse();
+        while let Some(body) = pending.pop() {
+            order.push(body);
+            pending.extend(self.children[body].iter().rev());
+        }
+
+        order
+    }
+
+    /// For every body, how many bodies its subtree holds, itself included.
+    pub fn subtree_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![1; self.len()];
+        for body in self.preorder().into_iter().rev() {
+            if let Some(parent) = self.parents[body] {
+                sizes[parent] += sizes[body];
+            }
+        }
+
+        sizes
+    }
+
+    /// The map in Graphviz DOT, each body pointing at the bodies orbiting
+    /// it. The bodies in `highlight`, such as a `path`, and the orbits
+    /// between them are drawn in red.
+    pub fn to_dot(&self, highlight: &[BodyId]) -> String {
+        let mut highlighted = vec![false; self.len()];
+        for &body in highlight {
+            highlighted[body] = true;
+        }
+        let quote = |body: BodyId| {
+            format!(
+                "\"{}\"",
+                self.name(body).replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        };
+
+        let mut dot = String::from("digraph orbits {\n");
+        for &body in highlight {
+            dot += &format!("    {} [color=red, fontcolor=red];\n", quote(body));
+        }
+        for body in self.preorder() {
+            if let Some(parent) = self.parents[body] {
+                let style = if highlighted[body] && highlighted[parent] {
+                    " [color=red, penwidth=2]"
+                } else {
+                    ""
+                };
+                dot += &format!("    {} -> {}{};\n", quote(parent), quote(body), style);
+            }
+        }
+        dot += "}\n";
+
+        dot
+    }
+
+    /// An indented text tree of the map, see `OrbitTree`.
+    pub fn tree(&self) -> OrbitTree<'_> {
+        OrbitTree {
+            map: self,
+            max_depth: None,
+            sizes: false,
+        }
     }
 }
 
-fn calc_orbits(
-    map: &std::collections::HashMap<String, Vec<String>>,
-    identity: &str,
-    indirects: u32,
-) -> u32 {
-    let node = map.get(identity);
-    match node {
-        Some(n) => {
-            n.iter()
-                .map(|n| calc_orbits(map, n, indirects + 1))
-                .sum::<u32>()
-                + indirects
-        }
-        None => indirects,
+/// An `OrbitMap` drawn as an indented tree with ASCII guides:
+///
+/// ```text
+/// COM (5)
+/// `-- B (4)
+///     |-- C (2)
+///     |   `-- D (1)
+///     `-- G (1)
+/// ```
+pub struct OrbitTree<'a> {
+    map: &'a OrbitMap,
+    max_depth: Option<usize>,
+    sizes: bool,
+}
+
+impl<'a> OrbitTree<'a> {
+    /// Stops at bodies `depth` orbits from a root, summarising what orbits
+    /// them as a count.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Shows the size of each body's subtree, the body included.
+    pub fn sizes(mut self, sizes: bool) -> Self {
+        self.sizes = sizes;
+        self
     }
 }
 
-fn calc_distance(
-    map: &std::collections::HashMap<String, (Vec<String>, Vec<String>)>,
-    start: String,
-    end: String,
-) -> usize {
-    let neighbors = |p: &String| -> Vec<String> {
-        let s = map.get(p).unwrap();
-        s.0.iter().chain(s.1.iter()).cloned().collect()
-    };
-
-    let success = |s: &String| -> bool { s == &end };
-    bfs(&start, neighbors, success).unwrap().len()
+impl<'a> fmt::Display for OrbitTree<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let map = self.map;
+        let sizes = map.subtree_sizes();
+
+        // (body, indent of its line, guide leading to it)
+        let mut pending: Vec<(BodyId, String, &str)> =
+            map.roots().map(|root| (root, String::new(), "")).collect();
+        pending.reverse();
+        while let Some((body, indent, guide)) = pending.pop() {
+            write!(f, "{}{}{}", indent, guide, map.name(body))?;
+            if self.sizes {
+                write!(f, " ({})", sizes[body])?;
+            }
+            writeln!(f)?;
+
+            let indent = indent
+                + match guide {
+                    "" => "",
+                    "|-- " => "|   ",
+                    _ => "    ",
+                };
+            let children = map.children(body);
+            if children.is_empty() {
+                continue;
+            }
+            if self.max_depth.is_some_and(|depth| map.depth(body) >= depth) {
+                writeln!(f, "{}`-- ... {} more", indent, sizes[body] - 1)?;
+                continue;
+            }
+            for (i, &child) in children.iter().enumerate().rev() {
+                let guide = if i + 1 == children.len() {
+                    "`-- "
+                } else {
+                    "|-- "
+                };
+                pending.push((child, indent.clone(), guide));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[aoc(day6, part1)]
-fn part_one(input: &[OrbitRelation]) -> u32 {
-    let mut map: std::collections::HashMap<String, Vec<String>> = Default::default();
-    input.iter().for_each(|or| {
-        let entry = map.entry(or.parent.clone()).or_insert(vec![]);
-        entry.push(or.identity.clone());
-    });
-
-    calc_orbits(&map, "COM", 0)
+fn part_one(map: &OrbitMap) -> usize {
+    map.total_orbits()
 }
 
 #[aoc(day6, part2)]
-fn part_two(input: &[OrbitRelation]) -> usize {
-    let mut map: std::collections::HashMap<String, (Vec<String>, Vec<String>)> = Default::default();
-    input.iter().for_each(|or| {
-        let entry = map.entry(or.parent.clone()).or_insert((vec![], vec![]));
-        entry.0.push(or.identity.clone());
-        let entry = map.entry(or.identity.clone()).or_insert((vec![], vec![]));
-        entry.1.push(or.parent.clone());
-    });
-
-    calc_distance(&map, "YOU".into(), "SAN".into())
+fn part_two(map: &OrbitMap) -> usize {
+    map.transfers(map.id("YOU").unwrap(), map.id("SAN").unwrap())
+        .expect("YOU and SAN orbit the same system")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generator_input, InvalidOrbitMap, OrbitError, OrbitMap};
+
+    const EXAMPLE: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L";
+    const TRANSFER_EXAMPLE: &str =
+        "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN";
+
+    fn names(map: &OrbitMap, bodies: &[usize]) -> Vec<String> {
+        bodies
+            .iter()
+            .map(|&body| map.name(body).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_orbit_counts() {
+        let map = generator_input(EXAMPLE).unwrap();
+        assert_eq!(map.len(), 12);
+        assert_eq!(map.depth(map.id("L").unwrap()), 7);
+        assert_eq!(map.direct_orbits(), 11);
+        assert_eq!(map.indirect_orbits(), 31);
+        assert_eq!(map.total_orbits(), 42);
+    }
+
+    #[test]
+    fn test_ancestors_and_paths() {
+        let map = generator_input(TRANSFER_EXAMPLE).unwrap();
+        let id = |name| map.id(name).unwrap();
+
+        assert_eq!(map.parent(id("B")), Some(id("COM")));
+        assert_eq!(map.parent(id("COM")), None);
+        assert_eq!(
+            names(&map, &map.ancestors(id("E")).collect::<Vec<_>>()),
+            ["D", "C", "B", "COM"]
+        );
+        assert_eq!(
+            map.lowest_common_ancestor(id("YOU"), id("SAN")),
+            Some(id("D"))
+        );
+        assert_eq!(map.lowest_common_ancestor(id("H"), id("B")), Some(id("B")));
+
+        let path = map.path(id("YOU"), id("SAN")).unwrap();
+        assert_eq!(names(&map, &path), ["YOU", "K", "J", "E", "D", "I", "SAN"]);
+        assert_eq!(map.distance(id("YOU"), id("SAN")), Some(6));
+        assert_eq!(map.transfers(id("YOU"), id("SAN")), Some(4));
+        assert_eq!(map.path(id("C"), id("C")), Some(vec![id("C")]));
+    }
+
+    fn errors(input: &str, root: &str) -> Vec<OrbitError> {
+        match OrbitMap::parse_with_root(input, root) {
+            Err(InvalidOrbitMap(errors)) => errors,
+            Ok(_) => panic!("{:?} should not be a valid map", input),
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            errors("COM)B\nB-C\n\nB)C)D", "COM"),
+            [
+                OrbitError::Malformed {
+                    line: 2,
+                    text: "B-C".to_string()
+                },
+                OrbitError::Malformed {
+                    line: 4,
+                    text: "B)C)D".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            errors("COM)B\nCOM)C\nB)D\nC)D", "COM"),
+            [OrbitError::MultipleParents {
+                body: "D".to_string(),
+                first_line: 3,
+                line: 4
+            }]
+        );
+        assert_eq!(
+            errors("COM)A\nX)Y\nY)Z\nZ)X\nZ)W", "COM"),
+            [OrbitError::Cycle {
+                bodies: vec!["X".to_string(), "Z".to_string(), "Y".to_string()],
+                lines: vec![4, 3, 2],
+            }]
+        );
+        assert_eq!(
+            errors("COM)B\nX)Y", "COM"),
+            [OrbitError::Disconnected {
+                body: "X".to_string(),
+                line: 2
+            }]
+        );
+        assert_eq!(
+            errors("A)B", "COM"),
+            [OrbitError::MissingRoot("COM".to_string())]
+        );
+        assert_eq!(
+            errors("X)COM\nCOM)B", "COM"),
+            [
+                OrbitError::RootHasParent {
+                    root: "COM".to_string(),
+                    line: 1
+                },
+                OrbitError::Disconnected {
+                    body: "X".to_string(),
+                    line: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_root_and_deep_maps() {
+        let map = OrbitMap::parse_with_root("SUN)EARTH\nEARTH)MOON\n", "SUN").unwrap();
+        assert_eq!(map.total_orbits(), 3);
+
+        let chain: Vec<String> = (0..100_000).map(|i| format!("{}){}", i, i + 1)).collect();
+        let map = OrbitMap::parse_with_root(&chain.join("\n"), "0").unwrap();
+        assert_eq!(map.total_orbits(), 100_000 * 100_001 / 2);
+    }
+
+    #[test]
+    fn test_tree() {
+        let map = generator_input(TRANSFER_EXAMPLE).unwrap();
+        let expected = "\
+COM (14)
+`-- B (13)
+    |-- C (10)
+    |   `-- D (9)
+    |       |-- E (6)
+    |       |   `-- ... 5 more
+    |       `-- I (2)
+    |           `-- ... 1 more
+    `-- G (2)
+        `-- H (1)
+";
+        assert_eq!(map.tree().max_depth(4).sizes(true).to_string(), expected);
+
+        let map = generator_input("COM)B\nB)C\nCOM)D").unwrap();
+        assert_eq!(map.tree().to_string(), "COM\n|-- B\n|   `-- C\n`-- D\n");
+    }
+
+    #[test]
+    fn test_dot() {
+        let map = OrbitMap::parse_with_root("SUN)EARTH\nEARTH)\"MOON\"\nSUN)MARS", "SUN").unwrap();
+        let id = |name| map.id(name).unwrap();
+        let path = map.path(id("MARS"), id("\"MOON\"")).unwrap();
+        assert_eq!(
+            map.to_dot(&path),
+            "\
+digraph orbits {
+    \"MARS\" [color=red, fontcolor=red];
+    \"SUN\" [color=red, fontcolor=red];
+    \"EARTH\" [color=red, fontcolor=red];
+    \"\\\"MOON\\\"\" [color=red, fontcolor=red];
+    \"SUN\" -> \"EARTH\" [color=red, penwidth=2];
+    \"EARTH\" -> \"\\\"MOON\\\"\" [color=red, penwidth=2];
+    \"SUN\" -> \"MARS\" [color=red, penwidth=2];
+}
+"
+        );
+        assert_eq!(map.to_dot(&[]).lines().count(), 5);
+    }
 }
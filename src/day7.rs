@@ -1,4 +1,4 @@
-use crate::intcode::{Action, Program};
+use crate::intcode::{search, Action, Program};
 use itertools::Itertools;
 
 #[aoc_generator(day7)]
@@ -11,49 +11,59 @@ fn generator_input(input: &str) -> Vec<i64> {
 
 #[aoc(day7, part1)]
 fn part_one(data: &Vec<i64>) -> i64 {
-    (0..5)
-        .permutations(5)
-        .map(|sequence| solve_sequence(data, &sequence))
-        .max()
-        .expect("Failed to find max")
+    let amplifier = Program::new(data.to_vec(), vec![]);
+    let (_, signal) = search::best(
+        &amplifier,
+        (0..5).permutations(5),
+        |_, _| (),
+        |amplifier, sequence| solve_sequence(&amplifier, sequence),
+    )
+    .expect("Failed to find max");
+
+    signal
 }
 
 #[aoc(day7, part2)]
 fn part_two(data: &Vec<i64>) -> i64 {
-    (5..10)
-        .permutations(5)
-        .map(|sequence| solve_sequence_feedback(data, &sequence))
-        .max()
-        .expect("Failed to find max")
+    let amplifier = Program::new(data.to_vec(), vec![]);
+    let (_, signal) = search::best(
+        &amplifier,
+        (5..10).permutations(5),
+        |_, _| (),
+        |amplifier, sequence| solve_sequence_feedback(&amplifier, sequence),
+    )
+    .expect("Failed to find max");
+
+    signal
+}
+
+/// One copy of `amplifier` per phase setting, each with its phase queued.
+fn amplifiers(amplifier: &Program, sequence: &[i64]) -> Vec<Program> {
+    sequence
+        .iter()
+        .map(|&phase| {
+            let mut program = amplifier.clone();
+            program.write_input(phase);
+            program.halt_on_output()
+        })
+        .collect()
 }
 
-fn solve_sequence(data: &Vec<i64>, sequence: &[i64]) -> i64 {
-    let mut programs: Vec<Program> = vec![
-        Program::new(data.to_vec(), vec![sequence[0]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[1]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[2]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[3]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[4]]).halt_on_output(),
-    ];
+fn solve_sequence(amplifier: &Program, sequence: &[i64]) -> i64 {
+    let mut programs = amplifiers(amplifier, sequence);
 
     let mut input = 0;
-    for i in 0..5 {
-        programs[i].write_input(input);
-        programs[i].execute();
-        input = programs[i].get_output()[0];
+    for program in programs.iter_mut() {
+        program.write_input(input);
+        program.execute();
+        input = program.get_output()[0];
     }
 
     input
 }
 
-fn solve_sequence_feedback(data: &Vec<i64>, sequence: &[i64]) -> i64 {
-    let mut programs: Vec<Program> = vec![
-        Program::new(data.to_vec(), vec![sequence[0]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[1]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[2]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[3]]).halt_on_output(),
-        Program::new(data.to_vec(), vec![sequence[4]]).halt_on_output(),
-    ];
+fn solve_sequence_feedback(amplifier: &Program, sequence: &[i64]) -> i64 {
+    let mut programs = amplifiers(amplifier, sequence);
 
     let mut input = 0;
     let mut index = 0;
@@ -92,13 +102,15 @@ mod tests {
     fn test_solve_sequence() {
         let data = generator_input("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
         let sequence = vec![4, 3, 2, 1, 0];
-        assert_eq!(solve_sequence(&data, &sequence), 43210);
+        let amplifier = Program::new(data, vec![]);
+        assert_eq!(solve_sequence(&amplifier, &sequence), 43210);
     }
 
     #[test]
     fn test_solve_sequence_feedback() {
         let data = generator_input("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5");
         let sequence = vec![9, 8, 7, 6, 5];
-        assert_eq!(solve_sequence_feedback(&data, &sequence), 139_629_729);
+        let amplifier = Program::new(data, vec![]);
+        assert_eq!(solve_sequence_feedback(&amplifier, &sequence), 139_629_729);
     }
 }
@@ -1,102 +1,235 @@
-#[aoc_generator(day8)]
-fn generator_input(input: &str) -> Vec<u32>{
-    input.chars().map(|c| c.to_digit(10).expect("Failed to parse u32")).collect()
+use crate::image::{Image, BLACK, WHITE};
+use crate::ocr;
+use std::fmt;
+
+/// Size of the BIOS password image.
+pub const WIDTH: usize = 25;
+pub const HEIGHT: usize = 6;
+
+pub const BLACK_PIXEL: u8 = 0;
+pub const WHITE_PIXEL: u8 = 1;
+pub const TRANSPARENT_PIXEL: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum SpaceImageError {
+    ZeroSize,
+    InvalidDigit { index: usize, found: char },
+    NotDivisible { len: usize, layer_size: usize },
+}
+
+impl fmt::Display for SpaceImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpaceImageError::ZeroSize => write!(f, "Image width and height must be positive"),
+            SpaceImageError::InvalidDigit { index, found } => {
+                write!(f, "Invalid pixel {:?} at position {}", found, index)
+            }
+            SpaceImageError::NotDivisible { len, layer_size } => write!(
+                f,
+                "{} pixels do not split into layers of {}",
+                len, layer_size
+            ),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+impl std::error::Error for SpaceImageError {}
+
+/// How many times each digit 0-9 occurs in a layer.
+pub type Histogram = [usize; 10];
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Layer {
-    data: Vec<u32>
+    pub width: usize,
+    pub height: usize,
+    data: Vec<u8>,
 }
 
 impl Layer {
-    pub fn new(data: &[u32]) -> Layer {
-        Layer { data: data.to_vec() }
+    pub fn new(data: &[u8], width: usize, height: usize) -> Layer {
+        assert_eq!(data.len(), width * height, "Layer size mismatch");
+        Layer {
+            width,
+            height,
+            data: data.to_vec(),
+        }
     }
 
-    pub fn get_2d(&self, width: usize, height: usize) -> Vec<Vec<u32>>{
-        let mut layer = vec![];
-        for y in 0..height {
-            let mut row = vec![];
-            for x in 0..width {
-                row.push(self.data[y * width + x]);
-            }
-            layer.push(row);
-        }
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x]
+    }
 
-        layer
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.data.chunks(self.width)
     }
 
-    pub fn checksum(&self) -> (u32, u32){
-        let mut zeroes = 0;
-        let mut ones = 0;
-        let mut twos = 0;
-        for x in self.data.iter() {
-            match x {
-                0 => zeroes += 1,
-                1 => ones += 1,
-                2 => twos += 1,
-
-                _ => unimplemented!()
-            };
+    pub fn histogram(&self) -> Histogram {
+        let mut histogram = [0; 10];
+        for &digit in self.data.iter() {
+            histogram[digit as usize] += 1;
         }
 
-        (zeroes, ones * twos)
+        histogram
     }
-}
-
-#[aoc(day8, part1)]
-fn part_one(data: &[u32]) -> String {
-    let width = 25;
-    let height = 6;
 
-    solve_part_one(data, width, height)
+    /// Renders white pixels lit and everything else, including pixels left
+    /// transparent, dark.
+    pub fn to_image(&self) -> Image {
+        Image::from_fn(self.width, self.height, |x, y| {
+            if self.get(x, y) == WHITE_PIXEL {
+                WHITE
+            } else {
+                BLACK
+            }
+        })
+    }
 }
 
-#[aoc(day8, part2)]
-fn part_two(data: &[u32]) -> String {
-    let width = 25;
-    let height = 6;
-
-    solve_part_two(data, width, height)
+/// An image in the Space Image Format: a stack of equally sized layers of
+/// digits, the first layer in front.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceImage {
+    pub width: usize,
+    pub height: usize,
+    pub layers: Vec<Layer>,
 }
 
+impl SpaceImage {
+    /// Parses a string of digits, ignoring surrounding whitespace.
+    pub fn parse(input: &str, width: usize, height: usize) -> Result<SpaceImage, SpaceImageError> {
+        let digits = input
+            .trim()
+            .chars()
+            .enumerate()
+            .map(|(index, found)| match found.to_digit(10) {
+                Some(digit) => Ok(digit as u8),
+                None => Err(SpaceImageError::InvalidDigit { index, found }),
+            })
+            .collect::<Result<Vec<u8>, SpaceImageError>>()?;
+
+        SpaceImage::from_digits(&digits, width, height)
+    }
 
-fn solve_part_one(data: &[u32], width: usize, height: usize ) -> String {
-    let mut layers: Vec<(u32, u32)> = data.chunks(width * height).map(|w| Layer::new(w).checksum()).collect();
-    layers.sort_by(|a, b| a.0.cmp(&b.0));
+    pub fn from_digits(
+        digits: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<SpaceImage, SpaceImageError> {
+        let layer_size = width * height;
+        if layer_size == 0 {
+            return Err(SpaceImageError::ZeroSize);
+        }
+        if digits.is_empty() || !digits.len().is_multiple_of(layer_size) {
+            return Err(SpaceImageError::NotDivisible {
+                len: digits.len(),
+                layer_size,
+            });
+        }
 
-    format!("{:?}", layers[0])
-}
+        Ok(SpaceImage {
+            width,
+            height,
+            layers: digits
+                .chunks(layer_size)
+                .map(|layer| Layer::new(layer, width, height))
+                .collect(),
+        })
+    }
 
-fn solve_part_two(data: &[u32], width: usize, height: usize ) -> String {
-    let raw_layers: Vec<Layer> = data.chunks(width * height).map(|w| Layer::new(w)).collect();
-    let mut layers = vec![];
-    for layer in raw_layers.iter() {
-        layers.push(layer.get_2d(width, height));
+    pub fn histograms(&self) -> Vec<Histogram> {
+        self.layers.iter().map(Layer::histogram).collect()
     }
 
-    let mut top_layer = vec![vec![2; width]; height];
+    /// The number of 1 digits times the number of 2 digits on the layer with
+    /// the fewest 0 digits.
+    pub fn checksum(&self) -> usize {
+        let histogram = self
+            .histograms()
+            .into_iter()
+            .min_by_key(|histogram| histogram[0])
+            .expect("Images have at least one layer");
 
-    for layer in layers.iter() {
-        for (y, row) in layer.iter().enumerate() {
-            for (x, val) in row.iter().enumerate() {
-                top_layer[y][x] = match top_layer[y][x] {
-                    2 => *val,
-                    _ => top_layer[y][x]
-                };
-            }
-        }
+        histogram[1] * histogram[2]
     }
 
-    for row in top_layer {
-        println!("{:?}", row);
+    /// Stacks the layers, letting transparent pixels show what lies below.
+    /// Pixels transparent on every layer stay transparent.
+    pub fn composite(&self) -> Layer {
+        let data: Vec<u8> = (0..self.width * self.height)
+            .map(|i| {
+                self.layers
+                    .iter()
+                    .map(|layer| layer.data[i])
+                    .find(|&pixel| pixel != TRANSPARENT_PIXEL)
+                    .unwrap_or(TRANSPARENT_PIXEL)
+            })
+            .collect();
+
+        Layer::new(&data, self.width, self.height)
     }
+}
+
+#[aoc_generator(day8)]
+fn generator_input(input: &str) -> Result<SpaceImage, SpaceImageError> {
+    SpaceImage::parse(input, WIDTH, HEIGHT)
+}
 
-    println!("\n\n");
+#[aoc(day8, part1)]
+fn part_one(image: &SpaceImage) -> usize {
+    image.checksum()
+}
+
+#[aoc(day8, part2)]
+fn part_two(image: &SpaceImage) -> String {
+    ocr::read_or_show(&image.composite().to_image())
+}
 
+#[cfg(test)]
+mod tests {
+    use super::{SpaceImage, SpaceImageError};
 
+    #[test]
+    fn test_layers_and_histograms() {
+        let image = SpaceImage::parse("123456789012\n", 3, 2).unwrap();
+        assert_eq!(image.layers.len(), 2);
 
+        let rows: Vec<&[u8]> = image.layers[1].rows().collect();
+        assert_eq!(rows, vec![&[7, 8, 9][..], &[0, 1, 2][..]]);
 
-    format!("{:?}", "test")
+        let histograms = image.histograms();
+        assert_eq!(histograms[0], [0, 1, 1, 1, 1, 1, 1, 0, 0, 0]);
+        assert_eq!(histograms[1], [1, 1, 1, 0, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(image.checksum(), 1);
+    }
+
+    #[test]
+    fn test_composite() {
+        let image = SpaceImage::parse("0222112222120000", 2, 2).unwrap();
+        let composite = image.composite();
+        let rows: Vec<&[u8]> = composite.rows().collect();
+        assert_eq!(rows, vec![&[0, 1][..], &[1, 0][..]]);
+        assert_eq!(composite.to_image().to_string(), " █\n█ \n");
 
+        let hidden = SpaceImage::parse("22", 2, 1).unwrap();
+        assert_eq!(hidden.composite().get(1, 0), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            SpaceImage::parse("1234567", 3, 2),
+            Err(SpaceImageError::NotDivisible {
+                len: 7,
+                layer_size: 6
+            })
+        );
+        assert_eq!(
+            SpaceImage::parse("12a4", 2, 1),
+            Err(SpaceImageError::InvalidDigit {
+                index: 2,
+                found: 'a'
+            })
+        );
+        assert_eq!(SpaceImage::parse("", 0, 6), Err(SpaceImageError::ZeroSize));
+    }
 }
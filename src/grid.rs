@@ -0,0 +1,230 @@
+//! A sparse 2D grid for the puzzles that move things around an unbounded
+//! plane and want to draw the result.
+
+use crate::image::{Image, BLACK, WHITE};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+/// Which way increasing `y` points when the grid is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YAxis {
+    /// Screen coordinates: row 0 is at the top.
+    Down,
+    /// Cartesian coordinates: the largest `y` is at the top.
+    Up,
+}
+
+/// An inclusive bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: i64,
+    pub max_x: i64,
+    pub min_y: i64,
+    pub max_y: i64,
+}
+
+impl Bounds {
+    pub fn width(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max_y - self.min_y + 1) as usize
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseGrid<T> {
+    cells: HashMap<(i64, i64), T>,
+    y_axis: YAxis,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new(YAxis::Down)
+    }
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new(y_axis: YAxis) -> Self {
+        SparseGrid {
+            cells: HashMap::new(),
+            y_axis,
+        }
+    }
+
+    pub fn y_axis(&self) -> YAxis {
+        self.y_axis
+    }
+
+    pub fn insert(&mut self, x: i64, y: i64, value: T) -> Option<T> {
+        self.cells.insert((x, y), value)
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        self.cells.get(&(x, y))
+    }
+
+    pub fn get_or_insert(&mut self, x: i64, y: i64, default: T) -> &mut T {
+        self.cells.entry((x, y)).or_insert(default)
+    }
+
+    pub fn remove(&mut self, x: i64, y: i64) -> Option<T> {
+        self.cells.remove(&(x, y))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Every set cell as `((x, y), value)`, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = ((i64, i64), &T)> {
+        self.cells.iter().map(|(&position, value)| (position, value))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.values()
+    }
+
+    /// The smallest box containing every set cell, or `None` for an empty
+    /// grid.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut positions = self.cells.keys();
+        let &(x, y) = positions.next()?;
+        let mut bounds = Bounds {
+            min_x: x,
+            max_x: x,
+            min_y: y,
+            max_y: y,
+        };
+        for &(x, y) in positions {
+            bounds.min_x = bounds.min_x.min(x);
+            bounds.max_x = bounds.max_x.max(x);
+            bounds.min_y = bounds.min_y.min(y);
+            bounds.max_y = bounds.max_y.max(y);
+        }
+
+        Some(bounds)
+    }
+
+    /// The `y` coordinates of the bounding box in drawing order, top first.
+    fn rows(&self, bounds: &Bounds) -> Box<dyn Iterator<Item = i64>> {
+        match self.y_axis {
+            YAxis::Down => Box::new(bounds.min_y..=bounds.max_y),
+            YAxis::Up => Box::new((bounds.min_y..=bounds.max_y).rev()),
+        }
+    }
+
+    /// Draws the bounding box one character per cell, asking `cell` for the
+    /// character of every position, set or not.
+    pub fn render<F: Fn(Option<&T>) -> char>(&self, cell: F) -> Render<'_, T, F> {
+        Render { grid: self, cell }
+    }
+
+    /// Rasterises the bounding box, lighting the set cells for which `lit`
+    /// holds.
+    pub fn to_image<F: Fn(&T) -> bool>(&self, lit: F) -> Image {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Image::new(0, 0),
+        };
+
+        let rows: Vec<i64> = self.rows(&bounds).collect();
+        Image::from_fn(bounds.width(), bounds.height(), |x, y| {
+            match self.get(bounds.min_x + x as i64, rows[y]) {
+                Some(value) if lit(value) => WHITE,
+                _ => BLACK,
+            }
+        })
+    }
+}
+
+impl<T> FromIterator<((i64, i64), T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = ((i64, i64), T)>>(cells: I) -> Self {
+        SparseGrid {
+            cells: cells.into_iter().collect(),
+            y_axis: YAxis::Down,
+        }
+    }
+}
+
+/// A `SparseGrid` paired with its cell-to-character mapping, see
+/// `SparseGrid::render`.
+pub struct Render<'a, T, F> {
+    grid: &'a SparseGrid<T>,
+    cell: F,
+}
+
+impl<'a, T, F: Fn(Option<&T>) -> char> fmt::Display for Render<'a, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bounds = match self.grid.bounds() {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+
+        for y in self.grid.rows(&bounds) {
+            let row: String = (bounds.min_x..=bounds.max_x)
+                .map(|x| (self.cell)(self.grid.get(x, y)))
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bounds, SparseGrid, YAxis};
+
+    fn corners(y_axis: YAxis) -> SparseGrid<char> {
+        let mut grid = SparseGrid::new(y_axis);
+        grid.insert(-1, -1, 'a');
+        grid.insert(2, -1, 'b');
+        grid.insert(-1, 1, 'c');
+        grid
+    }
+
+    #[test]
+    fn test_bounds_are_inclusive() {
+        let bounds = corners(YAxis::Down).bounds().unwrap();
+        assert_eq!(
+            bounds,
+            Bounds {
+                min_x: -1,
+                max_x: 2,
+                min_y: -1,
+                max_y: 1,
+            }
+        );
+        assert_eq!((bounds.width(), bounds.height()), (4, 3));
+        assert!(bounds.contains(2, 1));
+        assert!(!bounds.contains(3, 0));
+        assert_eq!(SparseGrid::<char>::default().bounds(), None);
+    }
+
+    #[test]
+    fn test_render_orientation() {
+        let down = corners(YAxis::Down);
+        assert_eq!(
+            down.render(|c| *c.unwrap_or(&'.')).to_string(),
+            "a..b\n....\nc...\n"
+        );
+
+        let up = corners(YAxis::Up);
+        assert_eq!(
+            up.render(|c| *c.unwrap_or(&'.')).to_string(),
+            "c...\n....\na..b\n"
+        );
+        assert_eq!(up.to_image(|&c| c != 'b').to_string(), "█   \n    \n█   \n");
+    }
+}
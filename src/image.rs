@@ -0,0 +1,237 @@
+//! A small grayscale raster image shared by the puzzles that draw pictures,
+//! with plain PBM/PGM and PNG export that need no extra dependencies.
+
+use std::fmt;
+
+/// Row-major 8-bit grayscale pixels: 0 is black, 255 is white.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+pub const BLACK: u8 = 0;
+pub const WHITE: u8 = 255;
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![BLACK; width * height],
+        }
+    }
+
+    pub fn from_fn<F: Fn(usize, usize) -> u8>(width: usize, height: usize, pixel: F) -> Image {
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, pixel(x, y));
+            }
+        }
+
+        image
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.get(x, y) >= 128
+    }
+
+    /// Blows every pixel up into a `factor` by `factor` square.
+    pub fn scaled(&self, factor: usize) -> Image {
+        Image::from_fn(self.width * factor, self.height * factor, |x, y| {
+            self.get(x / factor, y / factor)
+        })
+    }
+
+    /// Plain (ASCII) PBM. PBM marks black pixels with 1, so lit pixels are 0.
+    pub fn to_pbm(&self) -> String {
+        let mut out = format!("P1\n{} {}\n", self.width, self.height);
+        for y in 0..self.height {
+            let row: Vec<&str> = (0..self.width)
+                .map(|x| if self.is_lit(x, y) { "0" } else { "1" })
+                .collect();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Plain (ASCII) PGM with a maximum value of 255.
+    pub fn to_pgm(&self) -> String {
+        let mut out = format!("P2\n{} {}\n255\n", self.width, self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            let row: Vec<String> = row.iter().map(|pixel| pixel.to_string()).collect();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// An 8-bit grayscale PNG. The image data is stored uncompressed, which
+    /// keeps the encoder tiny; puzzle images are only a few kilobytes anyway.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut png, b"IEND", &[]);
+
+        png
+    }
+
+    /// Writes the image in the format matching the extension of `path`:
+    /// `.pbm`, `.pgm` or `.png`.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let contents = match path.rsplit('.').next() {
+            Some("pbm") => self.to_pbm().into_bytes(),
+            Some("pgm") => self.to_pgm().into_bytes(),
+            Some("png") => self.to_png(),
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Unknown image format for {}", path),
+                ))
+            }
+        };
+
+        std::fs::write(path, contents)
+    }
+}
+
+impl fmt::Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| if self.is_lit(x, y) { '█' } else { ' ' })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// A zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(0xffff).collect()
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        out.push((i == blocks.len() - 1) as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, crc32, Image, BLACK, WHITE};
+
+    fn checker() -> Image {
+        Image::from_fn(3, 2, |x, y| if (x + y) % 2 == 0 { WHITE } else { BLACK })
+    }
+
+    #[test]
+    fn test_scaled() {
+        let image = checker().scaled(2);
+        assert_eq!((image.width, image.height), (6, 4));
+        assert_eq!(image.to_string(), "██  ██\n██  ██\n  ██  \n  ██  \n");
+    }
+
+    #[test]
+    fn test_netpbm() {
+        let image = checker();
+        assert_eq!(image.to_pbm(), "P1\n3 2\n0 1 0\n1 0 1\n");
+        assert_eq!(image.to_pgm(), "P2\n3 2\n255\n255 0 255\n0 255 0\n");
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_png_layout() {
+        let png = checker().to_png();
+
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+
+        // The IDAT holds the filtered rows verbatim in a single stored block.
+        let idat = &png[33..];
+        assert_eq!(&idat[4..8], b"IDAT");
+        assert_eq!(&idat[8..15], &[0x78, 0x01, 1, 8, 0, !8, !0]);
+        assert_eq!(&idat[15..23], &[0, 255, 0, 255, 0, 0, 255, 0]);
+    }
+}
@@ -1,4 +1,15 @@
-#[derive(Debug, Clone)]
+pub mod compiler;
+pub mod devices;
+pub mod opcodes;
+pub mod replay;
+pub mod search;
+
+use devices::{MappedDevice, SharedDevice};
+use opcodes::{Opcode, OpcodeError, OpcodeTable, ParameterKind, MAX_ARITY};
+use replay::{Event, Recording};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy)]
 pub enum Parameter {
     Position(usize),
     Relative(i64),
@@ -15,33 +26,30 @@ impl Parameter {
         }
     }
 
-    pub fn get(&self, memory: &Vec<i64>, relative_base: i64) -> i64 {
+    pub fn get(&self, memory: &[i64], relative_base: i64) -> i64 {
         match self {
             Parameter::Immediate(value) => *value,
             Parameter::Position(position) => memory[*position],
             Parameter::Relative(position) => memory[(position + relative_base) as usize],
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub enum Instruction {
-    Add(Parameter, Parameter, Parameter),
-    Multiply(Parameter, Parameter, Parameter),
-    Input(Parameter),
-    Output(Parameter),
-    JumpTrue(Parameter, Parameter),
-    JumpFalse(Parameter, Parameter),
-    LessThan(Parameter, Parameter, Parameter),
-    Equals(Parameter, Parameter, Parameter),
-    AdjustBase(Parameter),
-    Halt,
+    pub fn address(&self, relative_base: i64) -> Option<usize> {
+        match self {
+            Parameter::Immediate(_) => None,
+            Parameter::Position(position) => Some(*position),
+            Parameter::Relative(position) => Some((position + relative_base) as usize),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Action {
     Nothing,
     Output(i64),
+    /// Returned by `execute` when the next instruction is an input and the
+    /// input queue is empty; `write_input` and `execute` again to resume.
+    AwaitingInput,
     Halt,
 }
 
@@ -54,6 +62,11 @@ pub struct Program {
     halt_on_output: bool,
     output: Vec<i64>,
     relative_base: i64,
+    devices: Vec<MappedDevice>,
+    opcodes: Arc<OpcodeTable>,
+    exit_code: Option<i64>,
+    steps: u64,
+    recording: Option<Recording>,
 }
 
 impl Program {
@@ -66,9 +79,31 @@ impl Program {
             halt_on_output: false,
             output: vec![],
             relative_base: 0,
+            devices: vec![],
+            opcodes: OpcodeTable::shared(),
+            exit_code: None,
+            steps: 0,
+            recording: None,
         }
     }
 
+    pub fn with_opcodes(mut self, opcodes: OpcodeTable) -> Self {
+        self.opcodes = Arc::new(opcodes);
+        self
+    }
+
+    pub fn register_opcode(&mut self, code: i64, opcode: Opcode) -> Result<(), OpcodeError> {
+        Arc::make_mut(&mut self.opcodes).register(code, opcode)
+    }
+
+    pub fn exit_code(&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    pub fn set_exit_code(&mut self, code: i64) {
+        self.exit_code = Some(code);
+    }
+
     pub fn set_available_memory(&mut self, memory: usize) {
         self.data.resize(memory, 0);
     }
@@ -81,26 +116,75 @@ impl Program {
     pub fn read_input(&mut self) -> i64 {
         let value = self.input[self.input_pointer];
         self.input_pointer += 1;
+        self.record(Event::Input {
+            step: self.steps,
+            value,
+        });
         value
     }
 
+    /// Whether the next instruction is an input and the input queue is empty.
+    pub fn awaiting_input(&self) -> bool {
+        self.input_pointer >= self.input.len() && self.load(self.pointer) % 100 == 3
+    }
+
     pub fn write_input(&mut self, value: i64) {
         self.input.push(value);
     }
 
+    /// Maps `device` over the `len` addresses starting at `start`. Panics if
+    /// the range overlaps a device that is already mapped.
+    pub fn map_device(&mut self, start: usize, len: usize, device: SharedDevice) {
+        if let Some(existing) = self.devices.iter().find(|d| d.overlaps(start, len)) {
+            panic!(
+                "Device at {}..{} overlaps {:?}",
+                start,
+                start + len,
+                existing
+            );
+        }
+
+        self.devices.push(MappedDevice { start, len, device });
+    }
+
+    pub fn load(&self, address: usize) -> i64 {
+        for mapped in &self.devices {
+            if let Some(offset) = mapped.offset_of(address) {
+                return mapped.device.lock().unwrap().read(offset);
+            }
+        }
+
+        self.data[address]
+    }
+
+    pub fn store(&mut self, address: usize, value: i64) {
+        for mapped in &self.devices {
+            if let Some(offset) = mapped.offset_of(address) {
+                mapped.device.lock().unwrap().write(offset, value);
+                return;
+            }
+        }
+
+        self.data[address] = value;
+    }
+
+    pub fn value(&self, param: &Parameter) -> i64 {
+        match param.address(self.relative_base) {
+            Some(address) => self.load(address),
+            None => param.get(&self.data, self.relative_base),
+        }
+    }
+
     pub fn read(&mut self) -> i64 {
-        let value = self.data[self.pointer];
+        let value = self.load(self.pointer);
         self.pointer += 1;
         value
     }
 
     pub fn write(&mut self, value: i64, output: &Parameter) {
-        match output {
-            Parameter::Position(position) => self.data[*position] = value,
-            Parameter::Relative(position) => {
-                self.data[(*position + self.relative_base) as usize] = value
-            }
-            _ => unimplemented!(),
+        match output.address(self.relative_base) {
+            Some(address) => self.store(address, value),
+            None => unimplemented!(),
         };
     }
 
@@ -108,149 +192,102 @@ impl Program {
         self.pointer = position;
     }
 
-    pub fn step(&mut self) -> Action {
-        let instruction = self.read();
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
 
-        let op_code = instruction % 100;
-        let mode1 = (instruction / 100) % 10;
-        let mode2 = (instruction / 1000) % 10;
-        let mode3 = (instruction / 10000) % 10;
-
-        let instruction = match op_code {
-            1 => Instruction::Add(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-                Parameter::new(mode3, self.read()),
-            ),
-
-            2 => Instruction::Multiply(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-                Parameter::new(mode3, self.read()),
-            ),
-
-            3 => Instruction::Input(Parameter::new(mode1, self.read())),
-            4 => Instruction::Output(Parameter::new(mode1, self.read())),
-
-            5 => Instruction::JumpTrue(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-            ),
-
-            6 => Instruction::JumpFalse(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-            ),
-
-            7 => Instruction::LessThan(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-                Parameter::new(mode3, self.read()),
-            ),
-
-            8 => Instruction::Equals(
-                Parameter::new(mode1, self.read()),
-                Parameter::new(mode2, self.read()),
-                Parameter::new(mode3, self.read()),
-            ),
-
-            9 => Instruction::AdjustBase(Parameter::new(mode1, self.read())),
-
-            99 => Instruction::Halt,
-            _ => unimplemented!(),
-        };
+    pub fn adjust_relative_base(&mut self, delta: i64) {
+        self.relative_base += delta;
+    }
 
-        return match instruction {
-            Instruction::Add(lhs, rhs, output) => {
-                let value = lhs.get(&self.data, self.relative_base)
-                    + rhs.get(&self.data, self.relative_base);
-                self.write(value, &output);
-                Action::Nothing
-            }
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
 
-            Instruction::Multiply(lhs, rhs, output) => {
-                let value = lhs.get(&self.data, self.relative_base)
-                    * rhs.get(&self.data, self.relative_base);
-                self.write(value, &output);
-                Action::Nothing
-            }
+    /// Starts logging consumed inputs and produced outputs, discarding any
+    /// previous recording.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording::default());
+    }
 
-            Instruction::Input(input) => {
-                let value = self.read_input();
-                self.write(value, &input);
-                Action::Nothing
-            }
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
 
-            Instruction::Output(output) => {
-                let value = output.get(&self.data, self.relative_base);
-                Action::Output(value)
-            }
+    pub fn take_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
 
-            Instruction::JumpTrue(param, output) => {
-                if param.get(&self.data, self.relative_base) != 0 {
-                    self.jump(output.get(&self.data, self.relative_base) as usize);
-                }
+    fn record(&mut self, event: Event) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.events.push(event);
+        }
+    }
 
-                Action::Nothing
-            }
+    pub fn step(&mut self) -> Action {
+        let address = self.pointer;
+        let instruction = self.read();
+        let op_code = instruction % 100;
 
-            Instruction::JumpFalse(param, output) => {
-                if param.get(&self.data, self.relative_base) == 0 {
-                    self.jump(output.get(&self.data, self.relative_base) as usize);
-                }
+        // Holding our own reference to the table leaves `self` free for the
+        // instruction to mutate, without copying the opcode.
+        let opcodes = Arc::clone(&self.opcodes);
+        let opcode = match opcodes.get(op_code) {
+            Some(opcode) => opcode,
+            None => panic!("Unknown opcode {} at {}", instruction, address),
+        };
 
-                Action::Nothing
+        let mut mode_digits = instruction / 100;
+        let mut params = [Parameter::Immediate(0); MAX_ARITY];
+        for (kind, param) in opcode.params.iter().zip(params.iter_mut()) {
+            *param = Parameter::new(mode_digits % 10, self.read());
+            if let (ParameterKind::Write, Parameter::Immediate(_)) = (kind, &param) {
+                panic!(
+                    "Immediate write parameter for {} at {}",
+                    opcode.name, address
+                );
             }
+            mode_digits /= 10;
+        }
 
-            Instruction::LessThan(lhs, rhs, output) => {
-                let lhs = lhs.get(&self.data, self.relative_base);
-                let rhs = rhs.get(&self.data, self.relative_base);
-
-                if lhs < rhs {
-                    self.write(1, &output);
-                } else {
-                    self.write(0, &output);
-                }
-
-                Action::Nothing
+        let action = (opcode.execute)(self, &params[..opcode.arity()]);
+        match action {
+            Action::Output(value) => {
+                self.output.push(value);
+                self.record(Event::Output {
+                    step: self.steps,
+                    value,
+                });
             }
-
-            Instruction::Equals(lhs, rhs, output) => {
-                let lhs = lhs.get(&self.data, self.relative_base);
-                let rhs = rhs.get(&self.data, self.relative_base);
-
-                if lhs == rhs {
-                    self.write(1, &output);
-                } else {
-                    self.write(0, &output);
+            Action::Halt => {
+                if let Some(recording) = self.recording.as_mut() {
+                    recording.halted_at = Some(self.steps);
                 }
-
-                Action::Nothing
-            }
-
-            Instruction::AdjustBase(base) => {
-                self.relative_base += base.get(&self.data, self.relative_base);
-
-                Action::Nothing
             }
+            Action::Nothing | Action::AwaitingInput => (),
+        }
+        self.steps += 1;
 
-            Instruction::Halt => Action::Halt,
-        };
+        action
     }
 
     pub fn execute(&mut self) -> Action {
         loop {
+            if self.awaiting_input() {
+                return Action::AwaitingInput;
+            }
+
             match self.step() {
                 Action::Halt => return Action::Halt,
 
                 Action::Output(value) => {
-                    self.output.push(value);
                     if self.halt_on_output {
                         return Action::Output(value);
                     }
                 }
 
-                Action::Nothing => (),
+                Action::Nothing | Action::AwaitingInput => (),
             }
         }
     }
@@ -262,7 +299,7 @@ impl Program {
 
 #[cfg(test)]
 pub mod tests {
-    use super::{Parameter, Program};
+    use super::{Action, Parameter, Program};
     pub fn parse_input(input: &str) -> Vec<i64> {
         input
             .split(",")
@@ -324,6 +361,17 @@ pub mod tests {
         assert_eq!(program.data, vec![2, 3, 0, 6, 99])
     }
 
+    #[test]
+    pub fn test_awaiting_input() {
+        let data = parse_input("3,0,4,0,99");
+        let mut program = Program::new(data, vec![]);
+        assert_eq!(program.execute(), Action::AwaitingInput);
+
+        program.write_input(5);
+        assert_eq!(program.execute(), Action::Halt);
+        assert_eq!(program.get_output(), vec![5]);
+    }
+
     #[test]
     pub fn test_relative_base() {
         let data = parse_input("1102,34915192,34915192,7,4,7,99,0");
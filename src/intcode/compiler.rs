@@ -0,0 +1,1189 @@
+//! A compiler for a tiny imperative language that targets the intcode VM.
+//!
+//! ```text
+//! fn fact(n) {
+//!     if n < 2 { return 1; }
+//!     return n * fact(n - 1);
+//! }
+//!
+//! fn main() {
+//!     let n = input();
+//!     while n > 0 {
+//!         output(fact(n));
+//!         n = n - 1;
+//!     }
+//! }
+//! ```
+//!
+//! Every value is an `i64`. Variables are local to the block that declares
+//! them with `let` and can't shadow a variable that is still in scope, and the supported operators are `+ - *`, the
+//! comparisons `== != < > <= >=`, unary `-` and `!`. Comparisons evaluate
+//! to `1` or `0`, and any non-zero value is truthy. `input()` reads the next
+//! value from the program input and `output(x)` emits `x`.
+//!
+//! Functions use the relative base as their frame pointer. A frame holds
+//! the return address in slot 0, the parameters from slot 1 onwards, then
+//! the locals and expression temporaries. A caller writes the arguments and
+//! the return address just past its own frame, moves the relative base up
+//! by its frame size and jumps; the callee leaves its result in its slot 1
+//! and jumps back through slot 0, and the caller moves the base back down.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Number of zeroed memory cells appended after the code for the call stack.
+pub const DEFAULT_STACK_SIZE: usize = 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum CompileError {
+    UnexpectedCharacter {
+        line: usize,
+        character: char,
+    },
+    InvalidNumber {
+        line: usize,
+        literal: String,
+    },
+    UnexpectedToken {
+        line: usize,
+        found: String,
+        expected: &'static str,
+    },
+    UnexpectedEnd {
+        expected: &'static str,
+    },
+    DuplicateFunction(String),
+    UndefinedFunction {
+        function: String,
+        name: String,
+    },
+    ArityMismatch {
+        function: String,
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    DuplicateVariable {
+        function: String,
+        name: String,
+    },
+    UndefinedVariable {
+        function: String,
+        name: String,
+    },
+    MissingMain,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::UnexpectedCharacter { line, character } => {
+                write!(f, "Line {}: unexpected character '{}'", line, character)
+            }
+            CompileError::InvalidNumber { line, literal } => {
+                write!(f, "Line {}: invalid number '{}'", line, literal)
+            }
+            CompileError::UnexpectedToken {
+                line,
+                found,
+                expected,
+            } => {
+                write!(f, "Line {}: expected {}, found '{}'", line, expected, found)
+            }
+            CompileError::UnexpectedEnd { expected } => {
+                write!(f, "Unexpected end of input, expected {}", expected)
+            }
+            CompileError::DuplicateFunction(name) => {
+                write!(f, "Function '{}' is defined more than once", name)
+            }
+            CompileError::UndefinedFunction { function, name } => {
+                write!(
+                    f,
+                    "In '{}': call to undefined function '{}'",
+                    function, name
+                )
+            }
+            CompileError::ArityMismatch {
+                function,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "In '{}': '{}' takes {} argument(s) but {} were given",
+                function, name, expected, found
+            ),
+            CompileError::DuplicateVariable { function, name } => {
+                write!(
+                    f,
+                    "In '{}': variable '{}' is declared more than once",
+                    function, name
+                )
+            }
+            CompileError::UndefinedVariable { function, name } => {
+                write!(
+                    f,
+                    "In '{}': use of undeclared variable '{}'",
+                    function, name
+                )
+            }
+            CompileError::MissingMain => write!(f, "No 'fn main()' without parameters was found"),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Not,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Fn => write!(f, "fn"),
+            Token::Let => write!(f, "let"),
+            Token::If => write!(f, "if"),
+            Token::Else => write!(f, "else"),
+            Token::While => write!(f, "while"),
+            Token::Return => write!(f, "return"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::LBrace => write!(f, "{{"),
+            Token::RBrace => write!(f, "}}"),
+            Token::Comma => write!(f, ","),
+            Token::Semicolon => write!(f, ";"),
+            Token::Assign => write!(f, "="),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Equal => write!(f, "=="),
+            Token::NotEqual => write!(f, "!="),
+            Token::Less => write!(f, "<"),
+            Token::Greater => write!(f, ">"),
+            Token::LessEqual => write!(f, "<="),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::Not => write!(f, "!"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '0'..='9' => {
+                let mut literal = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    literal.push(c);
+                    chars.next();
+                }
+                let value = literal
+                    .parse::<i64>()
+                    .map_err(|_| CompileError::InvalidNumber { line, literal })?;
+                Token::Number(value)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "fn" => Token::Fn,
+                    "let" => Token::Let,
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    "while" => Token::While,
+                    "return" => Token::Return,
+                    _ => Token::Ident(word),
+                }
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '=' | '!' | '<' | '>' => {
+                let followed_by_equals = chars.peek() == Some(&'=');
+                if followed_by_equals {
+                    chars.next();
+                }
+                match (c, followed_by_equals) {
+                    ('=', false) => Token::Assign,
+                    ('=', true) => Token::Equal,
+                    ('!', false) => Token::Not,
+                    ('!', true) => Token::NotEqual,
+                    ('<', false) => Token::Less,
+                    ('<', true) => Token::LessEqual,
+                    ('>', false) => Token::Greater,
+                    _ => Token::GreaterEqual,
+                }
+            }
+            character => return Err(CompileError::UnexpectedCharacter { line, character }),
+        };
+
+        tokens.push((token, line));
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Variable(String),
+    Call(String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Expr, Vec<Statement>, Vec<Statement>),
+    While(Expr, Vec<Statement>),
+    Return(Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Statement>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_second(&self) -> Option<&Token> {
+        self.tokens.get(self.position + 1).map(|(token, _)| token)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<(Token, usize), CompileError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(CompileError::UnexpectedEnd { expected })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, wanted: Token, expected: &'static str) -> Result<(), CompileError> {
+        let (token, line) = self.next(expected)?;
+        if token == wanted {
+            Ok(())
+        } else {
+            Err(CompileError::UnexpectedToken {
+                line,
+                found: token.to_string(),
+                expected,
+            })
+        }
+    }
+
+    fn eat(&mut self, wanted: &Token) -> bool {
+        if self.peek() == Some(wanted) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self, expected: &'static str) -> Result<String, CompileError> {
+        match self.next(expected)? {
+            (Token::Ident(name), _) => Ok(name),
+            (token, line) => Err(CompileError::UnexpectedToken {
+                line,
+                found: token.to_string(),
+                expected,
+            }),
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Function>, CompileError> {
+        let mut functions = vec![];
+        while self.peek().is_some() {
+            functions.push(self.function()?);
+        }
+
+        Ok(functions)
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        self.expect(Token::Fn, "'fn'")?;
+        let name = self.ident("a function name")?;
+        self.expect(Token::LParen, "'('")?;
+        let mut params = vec![];
+        if !self.eat(&Token::RParen) {
+            loop {
+                params.push(self.ident("a parameter name")?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(Token::Comma, "',' or ')'")?;
+            }
+        }
+        let body = self.block()?;
+
+        Ok(Function { name, params, body })
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect(Token::LBrace, "'{'")?;
+        let mut statements = vec![];
+        while !self.eat(&Token::RBrace) {
+            statements.push(self.statement()?);
+        }
+
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement, CompileError> {
+        let statement = match self.peek() {
+            Some(Token::Let) => {
+                self.position += 1;
+                let name = self.ident("a variable name")?;
+                self.expect(Token::Assign, "'='")?;
+                Statement::Let(name, self.expr()?)
+            }
+            Some(Token::If) => return self.if_statement(),
+            Some(Token::While) => {
+                self.position += 1;
+                let condition = self.expr()?;
+                return Ok(Statement::While(condition, self.block()?));
+            }
+            Some(Token::Return) => {
+                self.position += 1;
+                if self.peek() == Some(&Token::Semicolon) {
+                    Statement::Return(Expr::Number(0))
+                } else {
+                    Statement::Return(self.expr()?)
+                }
+            }
+            Some(Token::Ident(_)) if self.peek_second() == Some(&Token::Assign) => {
+                let name = self.ident("a variable name")?;
+                self.position += 1;
+                Statement::Assign(name, self.expr()?)
+            }
+            _ => Statement::Expr(self.expr()?),
+        };
+        self.expect(Token::Semicolon, "';'")?;
+
+        Ok(statement)
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, CompileError> {
+        self.expect(Token::If, "'if'")?;
+        let condition = self.expr()?;
+        let then_branch = self.block()?;
+        let else_branch = if !self.eat(&Token::Else) {
+            vec![]
+        } else if self.peek() == Some(&Token::If) {
+            vec![self.if_statement()?]
+        } else {
+            self.block()?
+        };
+
+        Ok(Statement::If(condition, then_branch, else_branch))
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.additive()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Equal) => BinaryOp::Equal,
+                Some(Token::NotEqual) => BinaryOp::NotEqual,
+                Some(Token::Less) => BinaryOp::Less,
+                Some(Token::Greater) => BinaryOp::Greater,
+                Some(Token::LessEqual) => BinaryOp::LessEqual,
+                Some(Token::GreaterEqual) => BinaryOp::GreaterEqual,
+                _ => return Ok(lhs),
+            };
+            self.position += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.additive()?));
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Subtract,
+                _ => return Ok(lhs),
+            };
+            self.position += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.unary()?;
+        while self.eat(&Token::Star) {
+            lhs = Expr::Binary(BinaryOp::Multiply, Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.eat(&Token::Minus) {
+            return Ok(match self.unary()? {
+                Expr::Number(value) => Expr::Number(-value),
+                operand => Expr::Unary(UnaryOp::Negate, Box::new(operand)),
+            });
+        }
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        match self.next("an expression")? {
+            (Token::Number(value), _) => Ok(Expr::Number(value)),
+            (Token::Ident(name), _) => {
+                if !self.eat(&Token::LParen) {
+                    return Ok(Expr::Variable(name));
+                }
+                let mut args = vec![];
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(Token::Comma, "',' or ')'")?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            (Token::LParen, _) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(expr)
+            }
+            (token, line) => Err(CompileError::UnexpectedToken {
+                line,
+                found: token.to_string(),
+                expected: "an expression",
+            }),
+        }
+    }
+}
+
+/// Parses `source` into its function definitions without generating code.
+pub fn parse(source: &str) -> Result<Vec<Function>, CompileError> {
+    let tokens = tokenize(source)?;
+    Parser {
+        tokens,
+        position: 0,
+    }
+    .program()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    fn mode(self) -> i64 {
+        match self {
+            Operand::Immediate(_) => 1,
+            Operand::Relative(_) => 2,
+        }
+    }
+
+    fn value(self) -> i64 {
+        match self {
+            Operand::Immediate(value) | Operand::Relative(value) => value,
+        }
+    }
+}
+
+const ADD: i64 = 1;
+const MULTIPLY: i64 = 2;
+const INPUT: i64 = 3;
+const OUTPUT: i64 = 4;
+const JUMP_TRUE: i64 = 5;
+const JUMP_FALSE: i64 = 6;
+const LESS_THAN: i64 = 7;
+const EQUALS: i64 = 8;
+const ADJUST_BASE: i64 = 9;
+const HALT: i64 = 99;
+
+struct FunctionEmitter<'a> {
+    code: &'a mut Vec<i64>,
+    arities: &'a HashMap<String, usize>,
+    call_fixups: &'a mut Vec<(usize, String)>,
+    name: &'a str,
+    variables: HashMap<String, i64>,
+    locals_end: i64,
+    next_slot: i64,
+    frame_size: i64,
+    frame_fixups: Vec<(usize, i64, i64)>,
+}
+
+impl<'a> FunctionEmitter<'a> {
+    fn emit(&mut self, opcode: i64, params: &[Operand]) -> usize {
+        let start = self.code.len();
+        let modes: i64 = params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| param.mode() * 10i64.pow(i as u32 + 2))
+            .sum();
+        self.code.push(opcode + modes);
+        self.code.extend(params.iter().map(|param| param.value()));
+        start
+    }
+
+    /// Records that the word at `index` must become `sign * frame_size + addend`
+    /// once the size of this function's frame is known.
+    fn frame_fixup(&mut self, index: usize, sign: i64, addend: i64) {
+        self.frame_fixups.push((index, sign, addend));
+    }
+
+    fn here(&self) -> i64 {
+        self.code.len() as i64
+    }
+
+    fn temp(&mut self) -> i64 {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.frame_size = self.frame_size.max(self.next_slot);
+        slot
+    }
+
+    fn variable(&self, name: &str) -> Result<i64, CompileError> {
+        self.variables
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CompileError::UndefinedVariable {
+                function: self.name.to_string(),
+                name: name.to_string(),
+            })
+    }
+
+    fn copy(&mut self, value: Operand, dest: i64) {
+        self.emit(
+            ADD,
+            &[value, Operand::Immediate(0), Operand::Relative(dest)],
+        );
+    }
+
+    fn operand(&mut self, expr: &Expr) -> Result<Operand, CompileError> {
+        match expr {
+            Expr::Number(value) => Ok(Operand::Immediate(*value)),
+            Expr::Variable(name) => Ok(Operand::Relative(self.variable(name)?)),
+            _ => {
+                let slot = self.temp();
+                self.expr_into(expr, slot)?;
+                Ok(Operand::Relative(slot))
+            }
+        }
+    }
+
+    /// Evaluates `expr` into frame slot `dest`. `dest` is only written by the
+    /// last instruction emitted, so it may alias a variable read by `expr`.
+    fn expr_into(&mut self, expr: &Expr, dest: i64) -> Result<(), CompileError> {
+        match expr {
+            Expr::Number(_) | Expr::Variable(_) => {
+                let value = self.operand(expr)?;
+                self.copy(value, dest);
+            }
+
+            Expr::Unary(UnaryOp::Negate, operand) => {
+                let value = self.operand(operand)?;
+                self.emit(
+                    MULTIPLY,
+                    &[value, Operand::Immediate(-1), Operand::Relative(dest)],
+                );
+            }
+
+            Expr::Unary(UnaryOp::Not, operand) => {
+                let value = self.operand(operand)?;
+                self.emit(
+                    EQUALS,
+                    &[value, Operand::Immediate(0), Operand::Relative(dest)],
+                );
+            }
+
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                let dest = Operand::Relative(dest);
+                match op {
+                    BinaryOp::Add => {
+                        self.emit(ADD, &[lhs, rhs, dest]);
+                    }
+                    BinaryOp::Subtract => {
+                        let negated = self.temp();
+                        self.emit(
+                            MULTIPLY,
+                            &[rhs, Operand::Immediate(-1), Operand::Relative(negated)],
+                        );
+                        self.emit(ADD, &[lhs, Operand::Relative(negated), dest]);
+                    }
+                    BinaryOp::Multiply => {
+                        self.emit(MULTIPLY, &[lhs, rhs, dest]);
+                    }
+                    BinaryOp::Equal => {
+                        self.emit(EQUALS, &[lhs, rhs, dest]);
+                    }
+                    BinaryOp::Less => {
+                        self.emit(LESS_THAN, &[lhs, rhs, dest]);
+                    }
+                    BinaryOp::Greater => {
+                        self.emit(LESS_THAN, &[rhs, lhs, dest]);
+                    }
+                    BinaryOp::NotEqual | BinaryOp::LessEqual | BinaryOp::GreaterEqual => {
+                        let inverse = self.temp();
+                        let (opcode, a, b) = match op {
+                            BinaryOp::NotEqual => (EQUALS, lhs, rhs),
+                            BinaryOp::LessEqual => (LESS_THAN, rhs, lhs),
+                            _ => (LESS_THAN, lhs, rhs),
+                        };
+                        self.emit(opcode, &[a, b, Operand::Relative(inverse)]);
+                        self.emit(
+                            EQUALS,
+                            &[Operand::Relative(inverse), Operand::Immediate(0), dest],
+                        );
+                    }
+                }
+            }
+
+            Expr::Call(name, args) => self.call(name, args, dest)?,
+        }
+
+        Ok(())
+    }
+
+    fn check_arity(&self, name: &str, expected: usize, found: usize) -> Result<(), CompileError> {
+        if expected == found {
+            Ok(())
+        } else {
+            Err(CompileError::ArityMismatch {
+                function: self.name.to_string(),
+                name: name.to_string(),
+                expected,
+                found,
+            })
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], dest: i64) -> Result<(), CompileError> {
+        match name {
+            "input" => {
+                self.check_arity(name, 0, args.len())?;
+                self.emit(INPUT, &[Operand::Relative(dest)]);
+                return Ok(());
+            }
+            "output" => {
+                self.check_arity(name, 1, args.len())?;
+                let value = self.operand(&args[0])?;
+                self.emit(OUTPUT, &[value]);
+                self.copy(Operand::Immediate(0), dest);
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let arity = *self
+            .arities
+            .get(name)
+            .ok_or_else(|| CompileError::UndefinedFunction {
+                function: self.name.to_string(),
+                name: name.to_string(),
+            })?;
+        self.check_arity(name, arity, args.len())?;
+
+        // Arguments may themselves contain calls, so evaluate all of them
+        // before writing into the callee's frame.
+        let values = args
+            .iter()
+            .map(|arg| self.operand(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, value) in values.into_iter().enumerate() {
+            let start = self.emit(ADD, &[value, Operand::Immediate(0), Operand::Relative(0)]);
+            self.frame_fixup(start + 3, 1, 1 + i as i64);
+        }
+
+        let start = self.emit(
+            ADD,
+            &[
+                Operand::Immediate(0),
+                Operand::Immediate(0),
+                Operand::Relative(0),
+            ],
+        );
+        self.frame_fixup(start + 3, 1, 0);
+        let adjust = self.emit(ADJUST_BASE, &[Operand::Immediate(0)]);
+        self.frame_fixup(adjust + 1, 1, 0);
+        let jump = self.emit(JUMP_TRUE, &[Operand::Immediate(1), Operand::Immediate(0)]);
+        self.call_fixups.push((jump + 2, name.to_string()));
+        self.code[start + 1] = self.here();
+
+        let restore = self.emit(ADJUST_BASE, &[Operand::Immediate(0)]);
+        self.frame_fixup(restore + 1, -1, 0);
+        let result = self.emit(
+            ADD,
+            &[
+                Operand::Relative(0),
+                Operand::Immediate(0),
+                Operand::Relative(dest),
+            ],
+        );
+        self.frame_fixup(result + 1, 1, 1);
+
+        Ok(())
+    }
+
+    fn jump_if_false(&mut self, condition: &Expr) -> Result<usize, CompileError> {
+        let value = self.operand(condition)?;
+        let start = self.emit(JUMP_FALSE, &[value, Operand::Immediate(0)]);
+        Ok(start + 2)
+    }
+
+    fn jump(&mut self, target: i64) -> usize {
+        let start = self.emit(
+            JUMP_TRUE,
+            &[Operand::Immediate(1), Operand::Immediate(target)],
+        );
+        start + 2
+    }
+
+    fn ret(&mut self, value: &Expr) -> Result<(), CompileError> {
+        self.expr_into(value, 1)?;
+        self.emit(JUMP_TRUE, &[Operand::Immediate(1), Operand::Relative(0)]);
+        Ok(())
+    }
+
+    /// Compiles `statements` in a scope of their own: variables they declare
+    /// go out of scope, and their slots are free again, once the block ends.
+    fn block(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        let variables = self.variables.clone();
+        let locals_end = self.locals_end;
+        for statement in statements {
+            self.statement(statement)?;
+            self.next_slot = self.locals_end;
+        }
+        self.variables = variables;
+        self.locals_end = locals_end;
+        self.next_slot = locals_end;
+
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match statement {
+            Statement::Let(name, value) => {
+                if self.variables.contains_key(name) {
+                    return Err(CompileError::DuplicateVariable {
+                        function: self.name.to_string(),
+                        name: name.to_string(),
+                    });
+                }
+                let value = self.operand(value)?;
+                let slot = self.locals_end;
+                self.locals_end += 1;
+                self.next_slot = self.next_slot.max(self.locals_end);
+                self.frame_size = self.frame_size.max(self.locals_end);
+                self.copy(value, slot);
+                self.variables.insert(name.to_string(), slot);
+            }
+
+            Statement::Assign(name, value) => {
+                let slot = self.variable(name)?;
+                self.expr_into(value, slot)?;
+            }
+
+            Statement::If(condition, then_branch, else_branch) => {
+                let to_else = self.jump_if_false(condition)?;
+                self.block(then_branch)?;
+                if else_branch.is_empty() {
+                    self.code[to_else] = self.here();
+                } else {
+                    let to_end = self.jump(0);
+                    self.code[to_else] = self.here();
+                    self.block(else_branch)?;
+                    self.code[to_end] = self.here();
+                }
+            }
+
+            Statement::While(condition, body) => {
+                let start = self.here();
+                let to_end = self.jump_if_false(condition)?;
+                self.block(body)?;
+                self.jump(start);
+                self.code[to_end] = self.here();
+            }
+
+            Statement::Return(value) => self.ret(value)?,
+
+            Statement::Expr(expr) => {
+                let slot = self.temp();
+                self.expr_into(expr, slot)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compiles `source` into an intcode image with room for a
+/// [`DEFAULT_STACK_SIZE`] cell call stack after the code.
+///
+/// Every call in progress takes up one frame of the stack: the return
+/// address, parameters, locals and temporaries of the function that made
+/// it. Small recursive functions get a few hundred calls deep in the
+/// default stack. Nothing checks for overflow: the VM panics with an index
+/// out of bounds once a call runs past the end of memory. Use
+/// [`compile_with_stack`] to make room for deeper recursion.
+pub fn compile(source: &str) -> Result<Vec<i64>, CompileError> {
+    compile_with_stack(source, DEFAULT_STACK_SIZE)
+}
+
+/// Compiles `source` into an intcode image that can be handed straight to
+/// `Program::new`. Execution starts by calling `main`, and the program halts
+/// when `main` returns.
+pub fn compile_with_stack(source: &str, stack_size: usize) -> Result<Vec<i64>, CompileError> {
+    let functions = parse(source)?;
+
+    let mut arities = HashMap::new();
+    for function in &functions {
+        if ["input", "output"].contains(&function.name.as_str())
+            || arities
+                .insert(function.name.clone(), function.params.len())
+                .is_some()
+        {
+            return Err(CompileError::DuplicateFunction(function.name.clone()));
+        }
+    }
+    if arities.get("main") != Some(&0) {
+        return Err(CompileError::MissingMain);
+    }
+
+    // Entry point: set up the stack, call main with the frame at the stack
+    // base and halt when it returns.
+    let mut code = vec![109, 0, 21101, 9, 0, 0, 1105, 1, 0, HALT];
+    let mut call_fixups = vec![(8, "main".to_string())];
+    let mut addresses = HashMap::new();
+
+    for function in &functions {
+        addresses.insert(function.name.clone(), code.len() as i64);
+
+        let params = function.params.len() as i64;
+        let mut emitter = FunctionEmitter {
+            code: &mut code,
+            arities: &arities,
+            call_fixups: &mut call_fixups,
+            name: &function.name,
+            variables: HashMap::new(),
+            locals_end: 1 + params,
+            next_slot: 1 + params,
+            frame_size: 2.max(1 + params),
+            frame_fixups: vec![],
+        };
+        for (i, param) in function.params.iter().enumerate() {
+            if emitter
+                .variables
+                .insert(param.clone(), 1 + i as i64)
+                .is_some()
+            {
+                return Err(CompileError::DuplicateVariable {
+                    function: function.name.clone(),
+                    name: param.clone(),
+                });
+            }
+        }
+
+        emitter.block(&function.body)?;
+        emitter.ret(&Expr::Number(0))?;
+
+        let frame_size = emitter.frame_size;
+        for (index, sign, addend) in emitter.frame_fixups {
+            code[index] = sign * frame_size + addend;
+        }
+    }
+
+    for (index, name) in call_fixups {
+        code[index] = addresses[&name];
+    }
+    code[1] = code.len() as i64;
+    code.resize(code.len() + stack_size, 0);
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile, compile_with_stack, parse, CompileError};
+    use crate::intcode::Program;
+
+    fn run(source: &str, input: Vec<i64>) -> Vec<i64> {
+        let mut program = Program::new(compile(source).unwrap(), input);
+        program.execute();
+        program.get_output()
+    }
+
+    #[test]
+    fn test_echo() {
+        let source = "fn main() { output(input()); output(input()); }";
+        assert_eq!(run(source, vec![7, -3]), vec![7, -3]);
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        let source = "
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a + b * 2);
+                output((a + b) * 2);
+                output(a - b - 1);
+                output(-a);
+                output(b - (0 - a));
+            }";
+        assert_eq!(run(source, vec![10, 3]), vec![16, 26, 6, -10, 13]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let source = "
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a == b); output(a != b);
+                output(a < b); output(a > b);
+                output(a <= b); output(a >= b);
+                output(!a);
+            }";
+        assert_eq!(run(source, vec![2, 5]), vec![0, 1, 1, 0, 1, 0, 0]);
+        assert_eq!(run(source, vec![5, 5]), vec![1, 0, 0, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_if_else_and_while() {
+        let source = "
+            fn main() {
+                let n = input();
+                while n > 0 {
+                    if n == 2 {
+                        output(200);
+                    } else if n == 3 {
+                        output(300);
+                    } else {
+                        output(n);
+                    }
+                    n = n - 1;
+                }
+            }";
+        assert_eq!(run(source, vec![4]), vec![4, 300, 200, 1]);
+    }
+
+    #[test]
+    fn test_recursive_functions() {
+        let source = "
+            // Naive recursion exercises the frame layout.
+            fn fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            fn fact(n) {
+                if n < 2 { return 1; }
+                return n * fact(n - 1);
+            }
+
+            fn main() {
+                output(fib(input()));
+                output(fact(10));
+            }";
+        assert_eq!(run(source, vec![15]), vec![610, 3_628_800]);
+    }
+
+    #[test]
+    fn test_multiple_arguments_and_nested_calls() {
+        let source = "
+            fn sub(a, b) { return a - b; }
+            fn mac(acc, x, y) { return acc + x * y; }
+            fn noop() { }
+
+            fn main() {
+                output(sub(10, 4));
+                output(mac(sub(5, 3), sub(9, 2), 3));
+                output(noop());
+                let x = 1;
+                x = sub(x, sub(x, 5));
+                output(x);
+            }";
+        assert_eq!(run(source, vec![]), vec![6, 23, 0, 5]);
+    }
+
+    #[test]
+    fn test_block_scopes() {
+        let source = "
+            fn main() {
+                let c = input();
+                if c { let t = 1; output(t); } else { let t = 2; output(t); }
+                let t = 3;
+                output(t);
+            }";
+        assert_eq!(run(source, vec![1]), vec![1, 3]);
+        assert_eq!(run(source, vec![0]), vec![2, 3]);
+
+        assert_eq!(
+            compile("fn main() { if 0 { let y = 5; } output(y); }"),
+            Err(CompileError::UndefinedVariable {
+                function: "main".to_string(),
+                name: "y".to_string(),
+            })
+        );
+        assert_eq!(
+            compile("fn main() { let x = 1; if x { let x = 2; } }"),
+            Err(CompileError::DuplicateVariable {
+                function: "main".to_string(),
+                name: "x".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion_needs_a_bigger_stack() {
+        let source = "
+            fn depth(n) {
+                if n == 0 { return 0; }
+                return 1 + depth(n - 1);
+            }
+            fn main() { output(depth(input())); }";
+        assert_eq!(run(source, vec![100]), vec![100]);
+
+        let data = compile_with_stack(source, 8 * 1024).unwrap();
+        let mut program = Program::new(data, vec![1000]);
+        program.execute();
+        assert_eq!(program.get_output(), vec![1000]);
+    }
+
+    #[test]
+    fn test_parse_error_reports_line() {
+        let source = "fn main() {\n  let x = 1\n}";
+        assert_eq!(
+            parse(source),
+            Err(CompileError::UnexpectedToken {
+                line: 3,
+                found: "}".to_string(),
+                expected: "';'",
+            })
+        );
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        assert_eq!(compile("fn foo() {}"), Err(CompileError::MissingMain));
+        assert_eq!(
+            compile("fn main() { output(y); }"),
+            Err(CompileError::UndefinedVariable {
+                function: "main".to_string(),
+                name: "y".to_string(),
+            })
+        );
+        assert_eq!(
+            compile("fn f(a) {} fn main() { f(1, 2); }"),
+            Err(CompileError::ArityMismatch {
+                function: "main".to_string(),
+                name: "f".to_string(),
+                expected: 1,
+                found: 2,
+            })
+        );
+        assert_eq!(
+            compile("fn main() {} fn main() {}"),
+            Err(CompileError::DuplicateFunction("main".to_string()))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Peripheral devices that can be mapped into a `Program`'s address space.
+//!
+//! A device is attached with `Program::map_device` over a range of addresses.
+//! Every read or write the VM makes inside that range, whether through a
+//! position or a relative parameter, is forwarded to the device with the
+//! offset from the start of the range instead of touching `data`.
+//!
+//! Devices are shared through `Arc<Mutex<_>>` so the embedder can keep a
+//! handle and inspect the device after the program has run. Cloning a
+//! `Program` shares its devices with the clone.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+pub trait Device: Send {
+    fn read(&mut self, offset: usize) -> i64;
+    fn write(&mut self, offset: usize, value: i64);
+}
+
+pub type SharedDevice = Arc<Mutex<dyn Device>>;
+
+/// Wraps a device so that it can be handed to `Program::map_device` while
+/// the caller keeps its own typed handle.
+pub fn shared<D: Device + 'static>(device: D) -> (Arc<Mutex<D>>, SharedDevice) {
+    let handle = Arc::new(Mutex::new(device));
+    let shared: SharedDevice = handle.clone();
+    (handle, shared)
+}
+
+#[derive(Clone)]
+pub struct MappedDevice {
+    pub start: usize,
+    pub len: usize,
+    pub device: SharedDevice,
+}
+
+impl MappedDevice {
+    pub fn offset_of(&self, address: usize) -> Option<usize> {
+        if address >= self.start && address - self.start < self.len {
+            Some(address - self.start)
+        } else {
+            None
+        }
+    }
+
+    pub fn overlaps(&self, start: usize, len: usize) -> bool {
+        start < self.start + self.len && self.start < start + len
+    }
+}
+
+impl fmt::Debug for MappedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MappedDevice({}..{})", self.start, self.start + self.len)
+    }
+}
+
+/// A `width * height` grid of pixels followed by one control cell.
+///
+/// Pixels are stored row by row; zero is drawn as a space and any other value
+/// as a block. Writing to the control cell (offset `width * height`) counts
+/// a frame and, when `echo` is on, prints the frame to the terminal. Reading
+/// the control cell returns the number of frames flushed so far.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<i64>,
+    pub frames: i64,
+    pub echo: bool,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width * height],
+            frames: 0,
+            echo: false,
+        }
+    }
+
+    /// Number of addresses the framebuffer occupies, including the control cell.
+    pub fn mapped_len(&self) -> usize {
+        self.width * self.height + 1
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> i64 {
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl fmt::Display for Framebuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.pixels.chunks(self.width) {
+            let line: String = row
+                .iter()
+                .map(|&pixel| if pixel == 0 { ' ' } else { '█' })
+                .collect();
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: usize) -> i64 {
+        if offset == self.pixels.len() {
+            self.frames
+        } else {
+            self.pixels[offset]
+        }
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        if offset == self.pixels.len() {
+            self.frames += 1;
+            if self.echo {
+                // Clear the screen and home the cursor before each frame.
+                print!("\x1b[2J\x1b[H{}", self);
+            }
+        } else {
+            self.pixels[offset] = value;
+        }
+    }
+}
+
+/// A single cell that counts up by one every time it is read. Writing sets
+/// the counter.
+#[derive(Debug, Clone, Default)]
+pub struct Timer {
+    pub ticks: i64,
+}
+
+impl Device for Timer {
+    fn read(&mut self, _offset: usize) -> i64 {
+        let ticks = self.ticks;
+        self.ticks += 1;
+        ticks
+    }
+
+    fn write(&mut self, _offset: usize, value: i64) {
+        self.ticks = value;
+    }
+}
+
+/// A single cell that yields a deterministic pseudo random non-negative
+/// number on every read (xorshift64). Writing reseeds the generator.
+#[derive(Debug, Clone)]
+pub struct RandomSource {
+    state: u64,
+}
+
+impl RandomSource {
+    pub fn new(seed: u64) -> Self {
+        RandomSource { state: seed.max(1) }
+    }
+}
+
+impl Device for RandomSource {
+    fn read(&mut self, _offset: usize) -> i64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 1) as i64
+    }
+
+    fn write(&mut self, _offset: usize, value: i64) {
+        self.state = (value as u64).max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shared, Framebuffer, RandomSource, Timer};
+    use crate::intcode::Program;
+
+    #[test]
+    fn test_framebuffer_writes_bypass_data() {
+        let (framebuffer, device) = shared(Framebuffer::new(3, 2));
+        // Write 1 to (0, 0) and 7 to (2, 1) with position mode, then 5 to
+        // (1, 0) with relative mode, then flush.
+        let data = vec![
+            1101, 1, 0, 100, 1101, 7, 0, 105, 109, 90, 21101, 5, 0, 11, 1101, 0, 0, 106, 99,
+        ];
+        let mut program = Program::new(data.clone(), vec![]);
+        program.map_device(100, 7, device);
+        program.execute();
+
+        let framebuffer = framebuffer.lock().unwrap();
+        assert_eq!(framebuffer.pixels, vec![1, 5, 0, 0, 0, 7]);
+        assert_eq!(framebuffer.frames, 1);
+        assert_eq!(framebuffer.to_string(), "██ \n  █\n");
+        assert_eq!(program.data, data);
+    }
+
+    #[test]
+    fn test_device_reads() {
+        let (_, timer) = shared(Timer { ticks: 10 });
+        let (_, random) = shared(RandomSource::new(42));
+        // Output the timer twice and the random source twice.
+        let data = vec![4, 50, 4, 50, 4, 60, 4, 60, 99];
+        let mut program = Program::new(data, vec![]);
+        program.map_device(50, 1, timer);
+        program.map_device(60, 1, random);
+        program.execute();
+
+        let output = program.get_output();
+        assert_eq!(&output[0..2], &[10, 11]);
+        assert!(output[2] >= 0 && output[3] >= 0 && output[2] != output[3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_devices_are_rejected() {
+        let mut program = Program::new(vec![99], vec![]);
+        program.map_device(10, 5, shared(Timer::default()).1);
+        program.map_device(14, 2, shared(Timer::default()).1);
+    }
+}
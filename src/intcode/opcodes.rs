@@ -0,0 +1,359 @@
+//! The opcode table `Program::step` decodes instructions through.
+//!
+//! Every instruction, built in or not, is an `Opcode`: a name, the kind of
+//! each of its parameters and a callback that runs it. `Program::step` reads
+//! the parameters with the modes encoded in the instruction and hands them
+//! to the callback, so embedders can add instructions of their own with
+//! `Program::register_opcode`. A table built with `OpcodeTable::strict`
+//! refuses registrations and is exactly the Advent of Code VM.
+
+use super::{Action, Parameter, Program};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// The most parameters an instruction can take, so `Program::step` can
+/// decode them into a fixed array without allocating. The built-in
+/// instructions take at most three.
+pub const MAX_ARITY: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    /// A value the instruction reads; any mode is allowed.
+    Read,
+    /// An address the instruction writes to; immediate mode is rejected.
+    Write,
+}
+
+pub type Execute = Arc<dyn Fn(&mut Program, &[Parameter]) -> Action + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Opcode {
+    pub name: String,
+    pub params: Vec<ParameterKind>,
+    pub execute: Execute,
+}
+
+impl Opcode {
+    pub fn new<F>(name: &str, params: &[ParameterKind], execute: F) -> Self
+    where
+        F: Fn(&mut Program, &[Parameter]) -> Action + Send + Sync + 'static,
+    {
+        Opcode {
+            name: name.to_string(),
+            params: params.to_vec(),
+            execute: Arc::new(execute),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+}
+
+impl fmt::Debug for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opcode({}, {:?})", self.name, self.params)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OpcodeError {
+    Strict,
+    OutOfRange(i64),
+    AlreadyRegistered(i64, String),
+    TooManyParameters(i64, usize),
+}
+
+impl fmt::Display for OpcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpcodeError::Strict => write!(f, "Opcode table is strict"),
+            OpcodeError::OutOfRange(code) => write!(f, "Opcode {} is not in 1..=99", code),
+            OpcodeError::AlreadyRegistered(code, name) => {
+                write!(f, "Opcode {} is already registered as {}", code, name)
+            }
+            OpcodeError::TooManyParameters(code, arity) => write!(
+                f,
+                "Opcode {} takes {} parameters, at most {} are supported",
+                code, arity, MAX_ARITY
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OpcodeError {}
+
+#[derive(Debug, Clone)]
+pub struct OpcodeTable {
+    opcodes: Vec<Option<Opcode>>,
+    strict: bool,
+}
+
+impl OpcodeTable {
+    /// The built-in Advent of Code instructions, with room for more.
+    pub fn new() -> Self {
+        let mut table = OpcodeTable {
+            opcodes: vec![None; 100],
+            strict: false,
+        };
+
+        use ParameterKind::{Read, Write};
+        let builtins: Vec<(i64, Opcode)> = vec![
+            (1, Opcode::new("add", &[Read, Read, Write], add)),
+            (2, Opcode::new("multiply", &[Read, Read, Write], multiply)),
+            (3, Opcode::new("input", &[Write], input)),
+            (4, Opcode::new("output", &[Read], output)),
+            (5, Opcode::new("jump_true", &[Read, Read], jump_true)),
+            (6, Opcode::new("jump_false", &[Read, Read], jump_false)),
+            (7, Opcode::new("less_than", &[Read, Read, Write], less_than)),
+            (8, Opcode::new("equals", &[Read, Read, Write], equals)),
+            (9, Opcode::new("adjust_base", &[Read], adjust_base)),
+            (99, Opcode::new("halt", &[], halt)),
+        ];
+        for (code, opcode) in builtins {
+            table.register(code, opcode).unwrap();
+        }
+
+        table
+    }
+
+    /// The built-in table, built once and shared by every `Program` until
+    /// it registers instructions of its own.
+    pub fn shared() -> Arc<OpcodeTable> {
+        static SHARED: OnceLock<Arc<OpcodeTable>> = OnceLock::new();
+        Arc::clone(SHARED.get_or_init(|| Arc::new(OpcodeTable::new())))
+    }
+
+    /// The built-in instructions only; `register` always fails.
+    pub fn strict() -> Self {
+        OpcodeTable {
+            strict: true,
+            ..OpcodeTable::new()
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn register(&mut self, code: i64, opcode: Opcode) -> Result<(), OpcodeError> {
+        if self.strict {
+            return Err(OpcodeError::Strict);
+        }
+        if !(1..=99).contains(&code) {
+            return Err(OpcodeError::OutOfRange(code));
+        }
+        if opcode.arity() > MAX_ARITY {
+            return Err(OpcodeError::TooManyParameters(code, opcode.arity()));
+        }
+
+        let slot = &mut self.opcodes[code as usize];
+        if let Some(existing) = slot {
+            return Err(OpcodeError::AlreadyRegistered(code, existing.name.clone()));
+        }
+        *slot = Some(opcode);
+
+        Ok(())
+    }
+
+    pub fn get(&self, code: i64) -> Option<&Opcode> {
+        if (0..100).contains(&code) {
+            self.opcodes[code as usize].as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for OpcodeTable {
+    fn default() -> Self {
+        OpcodeTable::new()
+    }
+}
+
+fn add(program: &mut Program, params: &[Parameter]) -> Action {
+    let value = program.value(&params[0]) + program.value(&params[1]);
+    program.write(value, &params[2]);
+    Action::Nothing
+}
+
+fn multiply(program: &mut Program, params: &[Parameter]) -> Action {
+    let value = program.value(&params[0]) * program.value(&params[1]);
+    program.write(value, &params[2]);
+    Action::Nothing
+}
+
+fn input(program: &mut Program, params: &[Parameter]) -> Action {
+    let value = program.read_input();
+    program.write(value, &params[0]);
+    Action::Nothing
+}
+
+fn output(program: &mut Program, params: &[Parameter]) -> Action {
+    Action::Output(program.value(&params[0]))
+}
+
+fn jump_true(program: &mut Program, params: &[Parameter]) -> Action {
+    if program.value(&params[0]) != 0 {
+        program.jump(program.value(&params[1]) as usize);
+    }
+
+    Action::Nothing
+}
+
+fn jump_false(program: &mut Program, params: &[Parameter]) -> Action {
+    if program.value(&params[0]) == 0 {
+        program.jump(program.value(&params[1]) as usize);
+    }
+
+    Action::Nothing
+}
+
+fn less_than(program: &mut Program, params: &[Parameter]) -> Action {
+    let value = program.value(&params[0]) < program.value(&params[1]);
+    program.write(value as i64, &params[2]);
+    Action::Nothing
+}
+
+fn equals(program: &mut Program, params: &[Parameter]) -> Action {
+    let value = program.value(&params[0]) == program.value(&params[1]);
+    program.write(value as i64, &params[2]);
+    Action::Nothing
+}
+
+fn adjust_base(program: &mut Program, params: &[Parameter]) -> Action {
+    let delta = program.value(&params[0]);
+    program.adjust_relative_base(delta);
+    Action::Nothing
+}
+
+fn halt(_program: &mut Program, _params: &[Parameter]) -> Action {
+    Action::Halt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Opcode, OpcodeError, OpcodeTable, ParameterKind};
+    use crate::intcode::{Action, Program};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_print_debug() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let sink = log.clone();
+        let print_debug = Opcode::new(
+            "print_debug",
+            &[ParameterKind::Read],
+            move |program, params| {
+                sink.lock().unwrap().push(program.value(&params[0]));
+                Action::Nothing
+            },
+        );
+
+        // print_debug the immediate 5, then the value at address 0.
+        let mut program = Program::new(vec![110, 5, 10, 0, 99], vec![]);
+        program.register_opcode(10, print_debug).unwrap();
+        program.execute();
+
+        assert_eq!(*log.lock().unwrap(), vec![5, 110]);
+        assert!(program.get_output().is_empty());
+    }
+
+    #[test]
+    fn test_four_parameters() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let sink = log.clone();
+        let print_debug = Opcode::new(
+            "print_debug4",
+            &[ParameterKind::Read; 4],
+            move |program, params| {
+                let values = params.iter().map(|param| program.value(param));
+                sink.lock().unwrap().extend(values);
+                Action::Nothing
+            },
+        );
+
+        // Immediate 5, the value at address 0, immediate 7 and the value at
+        // address 3.
+        let mut program = Program::new(vec![10110, 5, 0, 7, 3, 99], vec![]);
+        program.register_opcode(10, print_debug).unwrap();
+        program.execute();
+
+        assert_eq!(*log.lock().unwrap(), vec![5, 10110, 7, 7]);
+    }
+
+    #[test]
+    fn test_halt_with_code() {
+        let halt_with_code = Opcode::new(
+            "halt_with_code",
+            &[ParameterKind::Read],
+            |program, params| {
+                let code = program.value(&params[0]);
+                program.set_exit_code(code);
+                Action::Halt
+            },
+        );
+
+        // Add 40 + 2 into address 7 and halt with it.
+        let mut program = Program::new(vec![1101, 40, 2, 7, 11, 7, 99, 0], vec![]);
+        program.register_opcode(11, halt_with_code).unwrap();
+
+        assert_eq!(program.execute(), Action::Halt);
+        assert_eq!(program.exit_code(), Some(42));
+    }
+
+    #[test]
+    fn test_write_parameters() {
+        let swap = Opcode::new(
+            "swap",
+            &[ParameterKind::Write, ParameterKind::Write],
+            |program, params| {
+                let a = program.value(&params[0]);
+                let b = program.value(&params[1]);
+                program.write(b, &params[0]);
+                program.write(a, &params[1]);
+                Action::Nothing
+            },
+        );
+
+        let mut program = Program::new(vec![12, 4, 1, 99, 7], vec![]);
+        program.register_opcode(12, swap).unwrap();
+        program.execute();
+
+        assert_eq!(program.data, vec![12, 7, 1, 99, 4]);
+    }
+
+    #[test]
+    fn test_registration_errors() {
+        let noop = Opcode::new("noop", &[], |_, _| Action::Nothing);
+
+        let mut table = OpcodeTable::new();
+        assert_eq!(
+            table.register(1, noop.clone()),
+            Err(OpcodeError::AlreadyRegistered(1, "add".to_string()))
+        );
+        assert_eq!(
+            table.register(100, noop.clone()),
+            Err(OpcodeError::OutOfRange(100))
+        );
+        assert_eq!(
+            table.register(0, noop.clone()),
+            Err(OpcodeError::OutOfRange(0))
+        );
+        let wide = Opcode::new("wide", &[ParameterKind::Read; 9], |_, _| Action::Nothing);
+        assert_eq!(
+            table.register(10, wide),
+            Err(OpcodeError::TooManyParameters(10, 9))
+        );
+
+        let mut program = Program::new(vec![99], vec![]).with_opcodes(OpcodeTable::strict());
+        assert_eq!(program.register_opcode(10, noop), Err(OpcodeError::Strict));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_immediate_write_parameter_panics() {
+        let mut program = Program::new(vec![11101, 1, 1, 0, 99], vec![]);
+        program.execute();
+    }
+}
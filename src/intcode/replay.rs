@@ -0,0 +1,357 @@
+//! Recording and deterministic replay of intcode sessions.
+//!
+//! `Program::start_recording` makes a program log every input it consumes
+//! and every output it produces, each tagged with the number of instructions
+//! executed before it. The resulting `Recording` can be written to a plain
+//! text file and later handed to `replay`, which feeds the same inputs to a
+//! fresh program and checks that it produces the same events at the same
+//! instruction counts.
+//!
+//! The file format has one event per line:
+//!
+//! ```text
+//! in 12 -1
+//! out 15 42
+//! end 30
+//! ```
+//!
+//! where `end` records the instruction count at which the program halted. It
+//! is left out when the session stopped before the program did.
+
+use super::{Action, Program};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Input { step: u64, value: i64 },
+    Output { step: u64, value: i64 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub events: Vec<Event>,
+    pub halted_at: Option<u64>,
+}
+
+impl Recording {
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Input { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            Event::Output { value, .. } => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Recording, RecordingParseError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| RecordingParseError::Io(error.to_string()))?;
+        contents.parse()
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            match event {
+                Event::Input { step, value } => writeln!(f, "in {} {}", step, value)?,
+                Event::Output { step, value } => writeln!(f, "out {} {}", step, value)?,
+            }
+        }
+        if let Some(step) = self.halted_at {
+            writeln!(f, "end {}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecordingParseError {
+    Io(String),
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for RecordingParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingParseError::Io(error) => write!(f, "Failed to read recording: {}", error),
+            RecordingParseError::InvalidLine(line, contents) => {
+                write!(f, "Invalid recording line {}: {}", line, contents)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecordingParseError {}
+
+impl FromStr for Recording {
+    type Err = RecordingParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut recording = Recording::default();
+        for (index, line) in s.lines().enumerate() {
+            let invalid = || RecordingParseError::InvalidLine(index + 1, line.to_string());
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [] => continue,
+                ["in", step, value] | ["out", step, value] => {
+                    let step = step.parse().map_err(|_| invalid())?;
+                    let value = value.parse().map_err(|_| invalid())?;
+                    recording.events.push(if parts[0] == "in" {
+                        Event::Input { step, value }
+                    } else {
+                        Event::Output { step, value }
+                    });
+                }
+                ["end", step] => {
+                    recording.halted_at = Some(step.parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(recording)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    /// The program produced `found` where the recording has `expected`.
+    Mismatch {
+        index: usize,
+        expected: Option<Event>,
+        found: Event,
+    },
+    /// The program wanted an input at `step` but the recording has none left.
+    InputExhausted { step: u64 },
+    /// The program halted at `step` before producing every recorded event.
+    HaltedEarly { step: u64, remaining: usize },
+    /// The program ran past the instruction count the recording halted at.
+    Overran { step: u64 },
+    /// The recording has no halt and the program ran past the last recorded
+    /// event at `step` without producing every recorded event.
+    Unfinished { step: u64, remaining: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Mismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Event #{} differs: expected {:?}, found {:?}",
+                index, expected, found
+            ),
+            ReplayError::InputExhausted { step } => {
+                write!(f, "Input requested at step {} but none recorded", step)
+            }
+            ReplayError::HaltedEarly { step, remaining } => write!(
+                f,
+                "Halted at step {} with {} recorded event(s) left",
+                step, remaining
+            ),
+            ReplayError::Overran { step } => {
+                write!(f, "Still running at step {} after the recorded halt", step)
+            }
+            ReplayError::Unfinished { step, remaining } => write!(
+                f,
+                "Passed the last recorded event at step {} with {} recorded event(s) left",
+                step, remaining
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Runs `program` from its current state on the inputs in `recording` and
+/// checks that it reproduces the recorded session exactly. Returns the
+/// program after it halts so that its memory and output can be inspected.
+///
+/// A recording without a halt, such as a session the player quit, is only
+/// checked up to its last event: the program is returned as soon as it has
+/// reproduced every recorded event.
+pub fn replay(mut program: Program, recording: &Recording) -> Result<Program, ReplayError> {
+    program.input = recording.inputs().collect();
+    program.start_recording();
+    let mut checked = 0;
+
+    let last_event = recording.events.iter().map(|event| match event {
+        Event::Input { step, .. } | Event::Output { step, .. } => *step,
+    });
+    let limit = recording
+        .halted_at
+        .unwrap_or_else(|| last_event.max().unwrap_or(0));
+
+    loop {
+        let step = program.steps();
+        if recording.halted_at.is_none() && checked == recording.events.len() {
+            return Ok(program);
+        }
+        if program.awaiting_input() {
+            return Err(ReplayError::InputExhausted { step });
+        }
+        if step > limit {
+            return Err(match recording.halted_at {
+                Some(_) => ReplayError::Overran { step },
+                None => ReplayError::Unfinished {
+                    step,
+                    remaining: recording.events.len() - checked,
+                },
+            });
+        }
+
+        let action = program.step();
+
+        let events = &program.recording().unwrap().events;
+        for (index, found) in events.iter().enumerate().skip(checked) {
+            let expected = recording.events.get(index).cloned();
+            if expected != Some(*found) {
+                return Err(ReplayError::Mismatch {
+                    index,
+                    expected,
+                    found: *found,
+                });
+            }
+        }
+        checked = events.len();
+
+        if action == Action::Halt {
+            if checked < recording.events.len() {
+                return Err(ReplayError::HaltedEarly {
+                    step,
+                    remaining: recording.events.len() - checked,
+                });
+            }
+            return Ok(program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, Event, Recording, ReplayError};
+    use crate::intcode::compiler::compile;
+    use crate::intcode::{Action, Program};
+
+    const DOUBLER: &str = "
+        fn main() {
+            let x = input();
+            while x != 0 {
+                output(x * 2);
+                x = input();
+            }
+        }";
+
+    /// Plays a session interactively, deciding each input from the last output.
+    fn record_session() -> (Vec<i64>, Recording) {
+        let data = compile(DOUBLER).unwrap();
+        let mut program = Program::new(data.clone(), vec![1]).halt_on_output();
+        program.start_recording();
+        while let Action::Output(value) = program.execute() {
+            program.write_input(if value > 20 { 0 } else { value + 1 });
+        }
+
+        (data, program.take_recording().unwrap())
+    }
+
+    #[test]
+    fn test_record() {
+        let (_, recording) = record_session();
+        let inputs: Vec<i64> = recording.inputs().collect();
+        let outputs: Vec<i64> = recording.outputs().collect();
+
+        assert_eq!(inputs, vec![1, 3, 7, 15, 0]);
+        assert_eq!(outputs, vec![2, 6, 14, 30]);
+        assert!(recording.halted_at.is_some());
+        match recording.events[..2] {
+            [Event::Input { step: a, .. }, Event::Output { step: b, .. }] => assert!(a < b),
+            _ => panic!("Unexpected events {:?}", recording.events),
+        }
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        let (data, recording) = record_session();
+        let parsed: Recording = recording.to_string().parse().unwrap();
+        assert_eq!(parsed, recording);
+
+        let program = replay(Program::new(data, vec![]), &parsed).unwrap();
+        assert_eq!(program.get_output(), vec![2, 6, 14, 30]);
+    }
+
+    #[test]
+    fn test_replay_detects_divergence() {
+        let (data, recording) = record_session();
+
+        let mut tampered = recording.clone();
+        if let Event::Input { ref mut value, .. } = tampered.events[2] {
+            *value = 4;
+        }
+        assert!(matches!(
+            replay(Program::new(data.clone(), vec![]), &tampered),
+            Err(ReplayError::Mismatch { index: 3, .. })
+        ));
+
+        let mut truncated = recording;
+        truncated.events.truncate(4);
+        assert!(matches!(
+            replay(Program::new(data.clone(), vec![]), &truncated),
+            Err(ReplayError::InputExhausted { .. })
+        ));
+
+        truncated.halted_at = None;
+        let program = replay(Program::new(data, vec![]), &truncated).unwrap();
+        assert_eq!(program.get_output(), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_replay_without_a_halt() {
+        // Outputs 7 and then jumps to itself forever.
+        let data = vec![104, 7, 1105, 1, 2];
+        let recording: Recording = "out 0 7\n".parse().unwrap();
+        let program = replay(Program::new(data.clone(), vec![]), &recording).unwrap();
+        assert_eq!(program.get_output(), vec![7]);
+
+        // A player who quits while the program waits for a fourth input.
+        let doubler = compile(DOUBLER).unwrap();
+        let mut session = Program::new(doubler.clone(), vec![]);
+        session.start_recording();
+        for value in &[1, 2, 3] {
+            session.write_input(*value);
+            assert_eq!(session.execute(), Action::AwaitingInput);
+        }
+        let quit = session.take_recording().unwrap();
+        assert_eq!(quit.halted_at, None);
+        let program = replay(Program::new(doubler, vec![]), &quit).unwrap();
+        assert_eq!(program.get_output(), vec![2, 4, 6]);
+
+        let recording: Recording = "out 0 7\nout 3 8\n".parse().unwrap();
+        assert_eq!(
+            replay(Program::new(data, vec![]), &recording).err(),
+            Some(ReplayError::Unfinished {
+                step: 4,
+                remaining: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!("in 1 2\nout x 3\n".parse::<Recording>().is_err());
+    }
+}
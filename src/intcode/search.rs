@@ -0,0 +1,156 @@
+//! Brute-force searches over patched copies of a `Program`, spread across
+//! threads.
+//!
+//! Every candidate gets its own clone of the base program, which `patch`
+//! adjusts (memory pokes, queued input) before the predicate or score
+//! function runs it. Results are deterministic: `first` returns the earliest
+//! matching candidate in generator order and `best` breaks score ties the
+//! same way, however the work was split between threads.
+
+use super::Program;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+fn thread_count(candidates: usize) -> usize {
+    thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(candidates)
+        .max(1)
+}
+
+/// Returns the first candidate, in generator order, whose patched program
+/// satisfies `predicate`. Threads stop picking up candidates that come after
+/// a match that has already been found.
+pub fn first<C, I, P, F>(base: &Program, candidates: I, patch: P, predicate: F) -> Option<C>
+where
+    I: IntoIterator<Item = C>,
+    C: Send + Sync,
+    P: Fn(&mut Program, &C) + Sync,
+    F: Fn(Program) -> bool + Sync,
+{
+    let candidates: Vec<C> = candidates.into_iter().collect();
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+
+    thread::scope(|scope| {
+        for _ in 0..thread_count(candidates.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= candidates.len() || index > found.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let mut program = base.clone();
+                patch(&mut program, &candidates[index]);
+                if predicate(program) {
+                    found.fetch_min(index, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let index = found.into_inner();
+    candidates.into_iter().nth(index)
+}
+
+/// Scores every candidate's patched program and returns the candidate with
+/// the highest score, preferring the earliest candidate on ties. `score` is
+/// also handed the candidate, for searches that build several machines from
+/// the patched program.
+pub fn best<C, S, I, P, F>(base: &Program, candidates: I, patch: P, score: F) -> Option<(C, S)>
+where
+    I: IntoIterator<Item = C>,
+    C: Send + Sync,
+    S: Ord + Send,
+    P: Fn(&mut Program, &C) + Sync,
+    F: Fn(Program, &C) -> S + Sync,
+{
+    let candidates: Vec<C> = candidates.into_iter().collect();
+    let next = AtomicUsize::new(0);
+
+    let best = thread::scope(|scope| {
+        let workers: Vec<_> = (0..thread_count(candidates.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut best: Option<(usize, S)> = None;
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= candidates.len() {
+                            return best;
+                        }
+
+                        let mut program = base.clone();
+                        patch(&mut program, &candidates[index]);
+                        let value = score(program, &candidates[index]);
+                        if best.as_ref().is_none_or(|(_, best)| value > *best) {
+                            best = Some((index, value));
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().unwrap())
+            .max_by(|(a_index, a), (b_index, b)| a.cmp(b).then(b_index.cmp(a_index)))
+    });
+
+    let (index, value) = best?;
+    candidates
+        .into_iter()
+        .nth(index)
+        .map(|candidate| (candidate, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best, first};
+    use crate::intcode::compiler::compile;
+    use crate::intcode::Program;
+
+    #[test]
+    fn test_first_returns_earliest_match() {
+        let base = Program::new(
+            compile("fn main() { output(input() * input()); }").unwrap(),
+            vec![],
+        );
+        let candidates = (1..20).flat_map(|a| (1..20).map(move |b| (a, b)));
+
+        let found = first(
+            &base,
+            candidates,
+            |program, &(a, b)| program.input = vec![a, b],
+            |mut program| {
+                program.execute();
+                program.get_output() == vec![36]
+            },
+        );
+
+        assert_eq!(found, Some((2, 18)));
+    }
+
+    #[test]
+    fn test_first_without_match() {
+        let base = Program::new(vec![104, 1, 99], vec![]);
+        assert_eq!(first(&base, 0..10, |_, _| (), |_| false), None);
+    }
+
+    #[test]
+    fn test_best_prefers_earliest_on_ties() {
+        // Writes the candidate into the immediate of `output` and runs it.
+        let base = Program::new(vec![104, 0, 99], vec![]);
+        let scored = best(
+            &base,
+            vec![3, -7, 8, 1, 9, 4],
+            |program, &value| program.data[1] = value,
+            |mut program, _| {
+                program.execute();
+                program.get_output()[0].abs() / 2
+            },
+        );
+
+        assert_eq!(scored, Some((8, 4)));
+        assert_eq!(best(&base, Vec::<i64>::new(), |_, _| (), |_, _| 0), None);
+    }
+}
@@ -3,19 +3,23 @@ extern crate aoc_runner_derive;
 
 use aoc_runner_derive::aoc_lib;
 
+pub mod cli;
 mod day1;
-mod day10;
-mod day11;
-mod day12;
+pub mod day10;
+pub mod day11;
+pub mod day12;
 mod day2;
-mod day3;
+pub mod day3;
 mod day4;
 mod day5;
-mod day6;
+pub mod day6;
 mod day7;
-mod day8;
+pub mod day8;
 mod day9;
-mod day13;
-mod intcode;
+pub mod grid;
+pub mod image;
+pub mod day13;
+pub mod intcode;
+pub mod ocr;
 
 aoc_lib! { year = 2019 }
@@ -0,0 +1,240 @@
+//! Reads the capital letters Advent of Code draws in its 4x6 block font.
+//!
+//! Letters are four pixels wide and six tall, and sit in five column cells:
+//! the fifth column is the gap before the next letter. Only the first four
+//! columns of a cell are compared, which also covers `Y`, whose right arm
+//! reaches into the gap.
+
+use crate::image::Image;
+use std::fmt;
+
+const GLYPHS: [(char, &str); 18] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+pub const CELL_WIDTH: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownGlyph {
+    /// Position of the letter cell, counting from zero.
+    pub index: usize,
+    /// The cell's pixels, one row per line, `#` for lit and `.` for dark.
+    pub bitmap: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    Blank,
+    TooShort(usize),
+    UnknownGlyphs(Vec<UnknownGlyph>),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Blank => write!(f, "Image has no lit pixels"),
+            OcrError::TooShort(height) => write!(
+                f,
+                "Lettering is {} pixels tall, glyphs need {}",
+                height, GLYPH_HEIGHT
+            ),
+            OcrError::UnknownGlyphs(glyphs) => {
+                for glyph in glyphs {
+                    writeln!(f, "Unknown glyph #{}:\n{}", glyph.index, glyph.bitmap)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+fn lit(image: &Image, x: isize, y: usize) -> bool {
+    x >= 0 && (x as usize) < image.width && image.is_lit(x as usize, y)
+}
+
+fn cell_pattern(image: &Image, left: isize, top: usize) -> String {
+    let mut pattern = String::with_capacity(GLYPH_WIDTH * GLYPH_HEIGHT);
+    for y in top..top + GLYPH_HEIGHT {
+        for x in left..left + GLYPH_WIDTH as isize {
+            pattern.push(if lit(image, x, y) { '#' } else { '.' });
+        }
+    }
+
+    pattern
+}
+
+fn glyph(pattern: &str) -> Option<char> {
+    GLYPHS
+        .iter()
+        .find(|(_, glyph)| *glyph == pattern)
+        .map(|(c, _)| *c)
+}
+
+/// Reads the letters at one cell alignment, collecting the cells that don't
+/// match any glyph.
+fn read_cells(image: &Image, left: isize, top: usize, right: usize) -> Result<String, OcrError> {
+    let mut text = String::new();
+    let mut unknown = vec![];
+
+    let mut x = left;
+    while x <= right as isize {
+        let pattern = cell_pattern(image, x, top);
+        match glyph(&pattern) {
+            Some(c) => text.push(c),
+            None => unknown.push(UnknownGlyph {
+                index: text.len() + unknown.len(),
+                bitmap: pattern
+                    .as_bytes()
+                    .chunks(GLYPH_WIDTH)
+                    .map(|row| String::from_utf8_lossy(row).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            }),
+        }
+        x += CELL_WIDTH as isize;
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs(unknown))
+    }
+}
+
+/// Reads the text spelled out by the lit pixels of `image`.
+///
+/// Blank margins are skipped. Because some letters (`I`, `J`, `Y`) leave
+/// their first column dark, the first lit column may sit up to three
+/// columns into the first cell, so every such alignment is tried before
+/// giving up.
+pub fn recognise(image: &Image) -> Result<String, OcrError> {
+    let lit_rows: Vec<usize> = (0..image.height)
+        .filter(|&y| (0..image.width).any(|x| image.is_lit(x, y)))
+        .collect();
+    let lit_columns: Vec<usize> = (0..image.width)
+        .filter(|&x| (0..image.height).any(|y| image.is_lit(x, y)))
+        .collect();
+
+    let (top, bottom) = match (lit_rows.first(), lit_rows.last()) {
+        (Some(&top), Some(&bottom)) => (top, bottom),
+        _ => return Err(OcrError::Blank),
+    };
+    let (left, right) = (lit_columns[0], *lit_columns.last().unwrap());
+
+    // Lettering shorter than a glyph may still fit if its top rows are blank.
+    let top = top.min(image.height.saturating_sub(GLYPH_HEIGHT));
+    if image.height - top < GLYPH_HEIGHT || bottom >= top + GLYPH_HEIGHT {
+        return Err(OcrError::TooShort(bottom - top + 1));
+    }
+
+    let first = read_cells(image, left as isize, top, right);
+    if first.is_ok() {
+        return first;
+    }
+    for shift in 1..GLYPH_WIDTH as isize {
+        if let Ok(text) = read_cells(image, left as isize - shift, top, right) {
+            return Ok(text);
+        }
+    }
+
+    first
+}
+
+/// The recognised text, or when some glyph is unknown, the error followed by
+/// the picture itself so the answer can still be read off by eye.
+pub fn read_or_show(image: &Image) -> String {
+    match recognise(image) {
+        Ok(text) => text,
+        Err(error) => format!("\n{}\n{}", error, image),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recognise, OcrError, UnknownGlyph, GLYPHS};
+    use crate::image::{Image, BLACK, WHITE};
+
+    fn image(rows: &[&str]) -> Image {
+        Image::from_fn(rows[0].len(), rows.len(), |x, y| {
+            if rows[y].as_bytes()[x] == b'#' {
+                WHITE
+            } else {
+                BLACK
+            }
+        })
+    }
+
+    #[test]
+    fn test_every_glyph() {
+        let width = GLYPHS.len() * 5;
+        let letters = Image::from_fn(width, 6, |x, y| {
+            let (_, glyph) = GLYPHS[x / 5];
+            if x % 5 < 4 && glyph.as_bytes()[y * 4 + x % 5] == b'#' {
+                WHITE
+            } else {
+                BLACK
+            }
+        });
+
+        assert_eq!(recognise(&letters), Ok("ABCEFGHIJKLOPRSUYZ".to_string()));
+    }
+
+    #[test]
+    fn test_margins_and_leading_blank_column() {
+        let letters = image(&[
+            "...........",
+            "...###.#..#",
+            "....#..#..#",
+            "....#..####",
+            "....#..#..#",
+            "....#..#..#",
+            "...###.#..#",
+            "...........",
+        ]);
+
+        assert_eq!(recognise(&letters), Ok("IH".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_glyph() {
+        let letters = image(&[
+            "#..#.#...",
+            "#..#.##..",
+            "####.#.#.",
+            "#..#.#..#",
+            "#..#.#.#.",
+            "#..#.##..",
+        ]);
+
+        assert_eq!(
+            recognise(&letters),
+            Err(OcrError::UnknownGlyphs(vec![UnknownGlyph {
+                index: 1,
+                bitmap: "#...\n##..\n#.#.\n#..#\n#.#.\n##..".to_string(),
+            }]))
+        );
+        assert_eq!(recognise(&Image::new(10, 6)), Err(OcrError::Blank));
+    }
+}